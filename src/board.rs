@@ -0,0 +1,207 @@
+//! Descriptions of the avionics board revisions that the flight computer firmware is built for.
+
+use crate::index::{AnalogChannel, ConfigFile, HardwareError, HardwareTable};
+use crate::PYRO_CHANNELS;
+
+use heapless::Vec;
+
+/// Everything the config verifier and the ground station need to know about a board revision
+///
+/// The firmware defines one of these as a `const` for each board revision it supports.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoardProfile {
+    /// The hardware revision of the board, e.g. 2 or 3
+    pub revision: u8,
+    /// Whether Pyro1, Pyro2, and Pyro3 are populated, in that order
+    pub pyro_channels: [bool; PYRO_CHANNELS],
+    /// The analog outputs (DAC/PWM) on the board
+    pub analog_channels: &'static [AnalogChannel],
+    /// The sensors that are populated on the board
+    pub sensors: SensorComplement,
+    /// The layout of the board's data logging flash
+    pub flash: FlashGeometry,
+}
+
+/// Which sensors are populated on a board
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SensorComplement {
+    pub barometer: bool,
+    pub high_g_accelerometer: bool,
+    pub low_g_accelerometer: bool,
+    pub gyroscope: bool,
+    pub gps: bool,
+}
+
+/// The layout of a board's data logging flash
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FlashGeometry {
+    /// The size of a single page in bytes
+    pub page_size: u32,
+    /// The number of pages in an erase block
+    pub pages_per_block: u32,
+    /// The number of erase blocks on the chip
+    pub block_count: u32,
+}
+
+impl FlashGeometry {
+    /// The total capacity of the flash in bytes
+    pub const fn capacity(&self) -> u64 {
+        self.page_size as u64 * self.pages_per_block as u64 * self.block_count as u64
+    }
+}
+
+/// Reasons a config is rejected by [`BoardProfile::check_config`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BoardError {
+    /// The config's hardware table describes a different board
+    HardwareMismatch,
+    /// The config uses a channel this board doesn't have
+    Hardware(HardwareError),
+    /// The board has more analog channels than a [`HardwareTable`] can describe
+    TooManyAnalogChannels,
+}
+
+impl From<HardwareError> for BoardError {
+    fn from(e: HardwareError) -> Self {
+        BoardError::Hardware(e)
+    }
+}
+
+impl BoardProfile {
+    /// Returns the hardware table describing this board's channels
+    ///
+    /// Returns `None` if the board has more analog channels than a [`HardwareTable`] can hold
+    pub fn hardware_table(&self) -> Option<HardwareTable> {
+        Some(HardwareTable {
+            pyro_channels: self.pyro_channels,
+            analog_channels: Vec::from_slice(self.analog_channels).ok()?,
+        })
+    }
+
+    /// Checks that `config` can run on this board
+    ///
+    /// If the config has a hardware table it must describe this board. Either way, every channel
+    /// the config uses must exist on this board.
+    pub fn check_config<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+        &self,
+        config: &ConfigFile<STATES, CHECKS, COMMANDS>,
+    ) -> Result<(), BoardError> {
+        let hardware = self
+            .hardware_table()
+            .ok_or(BoardError::TooManyAnalogChannels)?;
+
+        if !config.matches_hardware(&hardware) {
+            return Err(BoardError::HardwareMismatch);
+        }
+
+        config.check_channels(&hardware)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoardError, BoardProfile, FlashGeometry, SensorComplement};
+    use crate::index::{AnalogChannel, ConfigFile, HardwareError};
+
+    const BOARD: BoardProfile = BoardProfile {
+        revision: 3,
+        pyro_channels: [true, true, true],
+        analog_channels: &[AnalogChannel {
+            channel: 0,
+            min: 0,
+            max: 1023,
+        }],
+        sensors: SensorComplement {
+            barometer: true,
+            high_g_accelerometer: true,
+            low_g_accelerometer: true,
+            gyroscope: true,
+            gps: false,
+        },
+        flash: FlashGeometry {
+            page_size: 2048,
+            pages_per_block: 64,
+            block_count: 1024,
+        },
+    };
+
+    fn config_with_analog(value: u16) -> ConfigFile {
+        state_machine! {
+            default Armed;
+
+            state Armed {
+                command AnalogOut { channel: 0, value } after 0.0;
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_config() {
+        // Configs without a hardware table are checked against the board's channels
+        let mut config = config_with_analog(1023);
+        assert_eq!(BOARD.check_config(&config), Ok(()));
+
+        let config_out_of_range = config_with_analog(1024);
+        assert!(matches!(
+            BOARD.check_config(&config_out_of_range),
+            Err(BoardError::Hardware(HardwareError::AnalogOutOfRange { .. }))
+        ));
+
+        config.hardware = BOARD.hardware_table();
+        assert_eq!(BOARD.check_config(&config), Ok(()));
+
+        let mut other_board = BOARD;
+        other_board.pyro_channels = [true, true, false];
+        assert_eq!(
+            other_board.check_config(&config),
+            Err(BoardError::HardwareMismatch)
+        );
+    }
+
+    #[test]
+    fn test_check_config_reordered_channels() {
+        const CHANNELS: [AnalogChannel; 2] = [
+            AnalogChannel {
+                channel: 0,
+                min: 0,
+                max: 1023,
+            },
+            AnalogChannel {
+                channel: 1,
+                min: 1000,
+                max: 2000,
+            },
+        ];
+        let mut board = BOARD;
+        board.analog_channels = &CHANNELS;
+
+        // The config's table lists the same channels the other way round
+        let mut config = config_with_analog(1023);
+        let mut hardware = board.hardware_table().unwrap();
+        hardware.analog_channels.reverse();
+        config.hardware = Some(hardware);
+        assert_eq!(board.check_config(&config), Ok(()));
+
+        // A channel with a different range is still a different board
+        let mut hardware = board.hardware_table().unwrap();
+        hardware.analog_channels[1].max = 2047;
+        config.hardware = Some(hardware);
+        assert_eq!(
+            board.check_config(&config),
+            Err(BoardError::HardwareMismatch)
+        );
+
+        // As is a board with only one of the channels
+        config.hardware = board.hardware_table();
+        assert_eq!(
+            BOARD.check_config(&config),
+            Err(BoardError::HardwareMismatch)
+        );
+    }
+
+    #[test]
+    fn test_flash_capacity() {
+        assert_eq!(BOARD.flash.capacity(), 128 * 1024 * 1024);
+    }
+}
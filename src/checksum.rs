@@ -0,0 +1,267 @@
+//! Checksums used to verify the integrity of data sent to and stored by the flight computer.
+//!
+//! All of the software implementations here are table driven, with the tables computed at compile
+//! time. Firmware that has a hardware CRC peripheral can implement [`Checksum`] on top of it
+//! instead.
+//!
+//! The STM32 CRC unit computes [`Crc32Mpeg2`], but only over whole 32-bit words, each of which it
+//! processes most significant byte first. Its result matches the software implementation over a
+//! byte stream only if firmware loads each group of four bytes as a big-endian word (a plain
+//! little-endian load followed by `REV`), and only if the stream is a multiple of four bytes long.
+//! The peripheral has no way to take the last one to three bytes of any other stream, so data
+//! checked with it must be padded to a multiple of four bytes first.
+
+/// An incrementally computed checksum
+pub trait Checksum {
+    type Output: Copy + PartialEq;
+
+    /// Feeds `bytes` into the checksum
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Returns the checksum of all of the bytes fed in so far
+    fn finish(&self) -> Self::Output;
+
+    /// Resets the checksum to its initial state, as if no bytes had been fed in
+    fn reset(&mut self);
+
+    /// Computes the checksum of `bytes` in one go
+    fn checksum(bytes: &[u8]) -> Self::Output
+    where
+        Self: Default,
+    {
+        let mut checksum = Self::default();
+        checksum.update(bytes);
+        checksum.finish()
+    }
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF)
+#[derive(Debug, Copy, Clone)]
+pub struct Crc16 {
+    crc: u16,
+}
+
+/// CRC-32 as used by zlib and Ethernet (reflected polynomial 0xEDB88320)
+#[derive(Debug, Copy, Clone)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+/// CRC-32/MPEG-2 (polynomial 0x04C11DB7, not reflected, no final xor), which is what the STM32 CRC
+/// peripheral computes when fed big-endian words (see the [module documentation](self))
+#[derive(Debug, Copy, Clone)]
+pub struct Crc32Mpeg2 {
+    crc: u32,
+}
+
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC32_MPEG2_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Crc16 {
+    pub const fn new() -> Self {
+        Self { crc: 0xFFFF }
+    }
+}
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self { crc: 0xFFFF_FFFF }
+    }
+}
+
+impl Crc32Mpeg2 {
+    pub const fn new() -> Self {
+        Self { crc: 0xFFFF_FFFF }
+    }
+}
+
+impl Checksum for Crc16 {
+    type Output = u16;
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let index = ((self.crc >> 8) as u8 ^ byte) as usize;
+            self.crc = (self.crc << 8) ^ CRC16_TABLE[index];
+        }
+    }
+
+    fn finish(&self) -> u16 {
+        self.crc
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Checksum for Crc32 {
+    type Output = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let index = (self.crc as u8 ^ byte) as usize;
+            self.crc = (self.crc >> 8) ^ CRC32_TABLE[index];
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.crc
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Checksum for Crc32Mpeg2 {
+    type Output = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let index = ((self.crc >> 24) as u8 ^ byte) as usize;
+            self.crc = (self.crc << 8) ^ CRC32_MPEG2_TABLE[index];
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.crc
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Crc32Mpeg2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{Checksum, Crc16, Crc32, Crc32Mpeg2};
+
+    // The standard check input for CRC catalogues
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn test_check_values() {
+        assert_eq!(Crc16::checksum(CHECK), 0x29B1);
+        assert_eq!(Crc32::checksum(CHECK), 0xCBF4_3926);
+        assert_eq!(Crc32Mpeg2::checksum(CHECK), 0x0376_E6E7);
+    }
+
+    #[test]
+    fn test_incremental() {
+        let mut crc = Crc32::new();
+        crc.update(&CHECK[..4]);
+        crc.update(&CHECK[4..]);
+        assert_eq!(crc.finish(), Crc32::checksum(CHECK));
+
+        crc.reset();
+        assert_eq!(crc.finish(), Crc32::checksum(&[]));
+    }
+
+    /// Computes the CRC the way the STM32 CRC peripheral does, one 32-bit word at a time
+    pub(crate) fn stm32_crc(words: impl Iterator<Item = u32>) -> u32 {
+        let mut crc = 0xFFFF_FFFF_u32;
+        for word in words {
+            crc ^= word;
+            for _ in 0..32 {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04C1_1DB7
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    #[test]
+    fn test_stm32_word_order() {
+        let bytes = b"12345678abcdefgh";
+        let words = bytes.chunks_exact(4).map(|word| {
+            let word = [word[0], word[1], word[2], word[3]];
+            u32::from_be_bytes(word)
+        });
+        assert_eq!(stm32_crc(words), Crc32Mpeg2::checksum(bytes));
+
+        // Loading words little-endian gives a different result
+        let words = bytes.chunks_exact(4).map(|word| {
+            let word = [word[0], word[1], word[2], word[3]];
+            u32::from_le_bytes(word)
+        });
+        assert_ne!(stm32_crc(words), Crc32Mpeg2::checksum(bytes));
+    }
+}
@@ -0,0 +1,677 @@
+//! Parses config files written in TOML, with states referred to by name.
+//!
+//! ```toml
+//! default_state = "Poweron"
+//!
+//! [[states]]
+//! name = "Safe"
+//!
+//! [[states]]
+//! name = "Poweron"
+//!
+//! [[states.checks]]
+//! object = "Pyro1Continuity"
+//! value = false
+//! abort = "Safe"
+//! persistence = 0.1
+//!
+//! [[states.commands]]
+//! object = "Beacon"
+//! value = true
+//! time = { ticks = 500 }
+//!
+//! [states.timeout]
+//! time = 10.0
+//! transition = "Safe"
+//! ```
+//!
+//! Checks and commands use the same syntax as the human readable encoding of [`CheckData`] and
+//! [`CommandObject`]. Times are given in seconds, or in timer ticks as `{ ticks = N }`.
+
+use crate::human_readable::{
+    CheckDataRepr, CheckObject, CheckValue, CommandObjectRepr, CommandTarget, CommandValue,
+    ConditionType,
+};
+use crate::index::{self, ConfigFile, HardwareTable, StateIndex, StateName, StateTransition};
+use crate::{CheckData, CommandObject, Delay};
+
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
+use toml::Spanned;
+
+/// Reasons a config file's text is rejected by [`parse`]. Lines are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigTextError {
+    /// The text is not valid TOML, or doesn't have the structure of a config file
+    Syntax(toml::de::Error),
+    /// A state is referred to by a name that no state has
+    UnknownState { name: String, line: usize },
+    /// More than one state has this name
+    DuplicateState { name: String, line: usize },
+    /// A state's name is longer than [`MAX_STATE_NAME_LEN`](crate::MAX_STATE_NAME_LEN)
+    NameTooLong { name: String, line: usize },
+    /// A check or timeout has both a `transition` and an `abort`, or a timeout has neither
+    InvalidTransition { line: usize },
+    /// There are more states, or more checks or commands in a state, than a config can hold
+    TooLarge { line: usize },
+    /// A table has a key that config files don't use, which is usually a misspelling
+    UnknownKey { key: String, line: usize },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigText {
+    default_state: Spanned<String>,
+    #[serde(default)]
+    states: Vec<StateText>,
+    hardware: Option<HardwareTable>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateText {
+    name: Spanned<String>,
+    #[serde(default)]
+    checks: Vec<CheckText>,
+    #[serde(default)]
+    commands: Vec<CommandText>,
+    timeout: Option<TimeoutText>,
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "CheckFields")]
+struct CheckText {
+    data: CheckData,
+    transition: Option<Spanned<String>>,
+    abort: Option<Spanned<String>>,
+    persistence: Option<DelayText>,
+}
+
+// The fields of `CheckData` and `CommandObject` are spelled out rather than flattened into the text
+// structs, because serde can't reject unknown fields in a struct with a flattened field
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckFields {
+    object: CheckObject,
+    #[serde(rename = "type", default)]
+    condition: Option<ConditionType>,
+    value: CheckValue,
+    transition: Option<Spanned<String>>,
+    abort: Option<Spanned<String>>,
+    persistence: Option<DelayText>,
+}
+
+impl TryFrom<CheckFields> for CheckText {
+    type Error = &'static str;
+
+    fn try_from(fields: CheckFields) -> Result<Self, Self::Error> {
+        let data = CheckDataRepr {
+            object: fields.object,
+            condition: fields.condition,
+            value: fields.value,
+        };
+        Ok(Self {
+            data: data.try_into()?,
+            transition: fields.transition,
+            abort: fields.abort,
+            persistence: fields.persistence,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "CommandFields")]
+struct CommandText {
+    object: CommandObject,
+    time: DelayText,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommandFields {
+    object: CommandTarget,
+    channel: Option<u8>,
+    value: CommandValue,
+    time: DelayText,
+}
+
+impl TryFrom<CommandFields> for CommandText {
+    type Error = &'static str;
+
+    fn try_from(fields: CommandFields) -> Result<Self, Self::Error> {
+        let object = CommandObjectRepr {
+            object: fields.object,
+            channel: fields.channel,
+            value: fields.value,
+        };
+        Ok(Self {
+            object: object.try_into()?,
+            time: fields.time,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimeoutText {
+    // toml can't give the span of a table, so errors in a timeout are reported at its `time`
+    time: Spanned<DelayText>,
+    transition: Option<Spanned<String>>,
+    abort: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize, Copy, Clone)]
+#[serde(untagged, deny_unknown_fields)]
+enum DelayText {
+    Seconds(f32),
+    Ticks { ticks: u32 },
+}
+
+impl From<DelayText> for Delay {
+    fn from(delay: DelayText) -> Self {
+        match delay {
+            DelayText::Seconds(seconds) => Delay::Seconds(seconds),
+            DelayText::Ticks { ticks } => Delay::Ticks(ticks),
+        }
+    }
+}
+
+/// The keys of a table, along with any tables nested in their values
+///
+/// The text structs reject unknown keys too, but toml only knows the position of the last table
+/// header by then. Reading every key with its span first lets a misspelled key be reported at its
+/// own line.
+struct Keys(Vec<(Spanned<String>, Nested)>);
+
+enum Nested {
+    Table(Keys),
+    Array(Vec<Nested>),
+    Value,
+}
+
+impl Keys {
+    /// Returns the first key that isn't one of `allowed`
+    fn unknown(&self, allowed: &[&str]) -> Option<&Spanned<String>> {
+        self.0
+            .iter()
+            .map(|(key, _)| key)
+            .find(|key| !allowed.contains(&key.get_ref().as_str()))
+    }
+
+    /// Returns the table under `key`, or every table in an array of tables under `key`
+    fn tables(&self, key: &str) -> Vec<&Keys> {
+        let value = self.0.iter().find(|(k, _)| k.get_ref() == key);
+        match value {
+            Some((_, Nested::Table(table))) => vec![table],
+            Some((_, Nested::Array(items))) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Nested::Table(table) => Some(table),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+struct KeysVisitor;
+
+impl<'de> Visitor<'de> for KeysVisitor {
+    type Value = Nested;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a TOML value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Nested, A::Error> {
+        let mut keys = Vec::new();
+        while let Some(key) = map.next_key::<Spanned<String>>()? {
+            keys.push((key, map.next_value()?));
+        }
+        Ok(Nested::Table(Keys(keys)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Nested, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Nested::Array(items))
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Nested, E> {
+        Ok(Nested::Value)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Nested, E> {
+        Ok(Nested::Value)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Nested, E> {
+        Ok(Nested::Value)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Nested, E> {
+        Ok(Nested::Value)
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Nested, E> {
+        Ok(Nested::Value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Nested {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(KeysVisitor)
+    }
+}
+
+/// The keys each kind of table in a config file can have
+const CONFIG_KEYS: &[&str] = &["default_state", "states", "hardware"];
+const STATE_KEYS: &[&str] = &["name", "checks", "commands", "timeout"];
+const CHECK_KEYS: &[&str] = &[
+    "object",
+    "type",
+    "value",
+    "transition",
+    "abort",
+    "persistence",
+];
+const COMMAND_KEYS: &[&str] = &["object", "channel", "value", "time"];
+const TIMEOUT_KEYS: &[&str] = &["time", "transition", "abort"];
+const HARDWARE_KEYS: &[&str] = &["pyro_channels", "analog_channels"];
+const ANALOG_CHANNEL_KEYS: &[&str] = &["channel", "min", "max"];
+
+/// Returns the first key in `config` that a config file can't have
+fn unknown_key(config: &Keys) -> Option<&Spanned<String>> {
+    let mut unknown = vec![config.unknown(CONFIG_KEYS)];
+    for state in config.tables("states") {
+        unknown.push(state.unknown(STATE_KEYS));
+        for check in state.tables("checks") {
+            unknown.push(check.unknown(CHECK_KEYS));
+        }
+        for command in state.tables("commands") {
+            unknown.push(command.unknown(COMMAND_KEYS));
+        }
+        for timeout in state.tables("timeout") {
+            unknown.push(timeout.unknown(TIMEOUT_KEYS));
+        }
+    }
+    for hardware in config.tables("hardware") {
+        unknown.push(hardware.unknown(HARDWARE_KEYS));
+        for channel in hardware.tables("analog_channels") {
+            unknown.push(channel.unknown(ANALOG_CHANNEL_KEYS));
+        }
+    }
+
+    // Report the one closest to the top of the file
+    unknown.into_iter().flatten().min_by_key(|key| key.start())
+}
+
+/// Maps state names to their indices, and byte offsets in the text to line numbers
+struct Resolver<'a> {
+    text: &'a str,
+    states: HashMap<&'a str, StateIndex>,
+}
+
+impl<'a> Resolver<'a> {
+    fn line(&self, offset: usize) -> usize {
+        self.text[..offset].matches('\n').count() + 1
+    }
+
+    fn state(&self, name: &Spanned<String>) -> Result<StateIndex, ConfigTextError> {
+        self.states
+            .get(name.get_ref().as_str())
+            .copied()
+            .ok_or_else(|| ConfigTextError::UnknownState {
+                name: name.get_ref().clone(),
+                line: self.line(name.start()),
+            })
+    }
+
+    /// Resolves a `transition` or `abort` pair, of which at most one may be given
+    fn transition(
+        &self,
+        transition: &Option<Spanned<String>>,
+        abort: &Option<Spanned<String>>,
+    ) -> Result<Option<StateTransition>, ConfigTextError> {
+        match (transition, abort) {
+            (Some(_), Some(abort)) => Err(ConfigTextError::InvalidTransition {
+                line: self.line(abort.start()),
+            }),
+            (Some(name), None) => Ok(Some(StateTransition::Transition(self.state(name)?))),
+            (None, Some(name)) => Ok(Some(StateTransition::Abort(self.state(name)?))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// Parses the text of a config file, resolving state names to indices
+pub fn parse<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+    text: &str,
+) -> Result<ConfigFile<STATES, CHECKS, COMMANDS>, ConfigTextError> {
+    let mut resolver = Resolver {
+        text,
+        states: HashMap::new(),
+    };
+
+    let keys = match toml::from_str(text)? {
+        Nested::Table(keys) => keys,
+        // toml documents are always tables
+        _ => unreachable!(),
+    };
+    if let Some(key) = unknown_key(&keys) {
+        return Err(ConfigTextError::UnknownKey {
+            key: key.get_ref().clone(),
+            line: resolver.line(key.start()),
+        });
+    }
+
+    let config: ConfigText = toml::from_str(text)?;
+    for (i, state) in config.states.iter().enumerate() {
+        let line = resolver.line(state.name.start());
+        if i >= STATES {
+            return Err(ConfigTextError::TooLarge { line });
+        }
+
+        // # SAFETY: `i` is the index `state` will have in the config, and was checked above to be
+        // less than `STATES`
+        let idx = unsafe { StateIndex::new_unchecked(i as u8) };
+        if resolver.states.insert(state.name.get_ref(), idx).is_some() {
+            return Err(ConfigTextError::DuplicateState {
+                name: state.name.get_ref().clone(),
+                line,
+            });
+        }
+    }
+
+    let mut states = heapless::Vec::new();
+    for state in &config.states {
+        let too_large = ConfigTextError::TooLarge {
+            line: resolver.line(state.name.start()),
+        };
+
+        let mut checks = heapless::Vec::new();
+        for check in &state.checks {
+            let check = index::Check {
+                data: check.data,
+                transition: resolver.transition(&check.transition, &check.abort)?,
+                persistence: check.persistence.map(Delay::from),
+            };
+            checks.push(check).map_err(|_| too_large.clone())?;
+        }
+
+        let mut commands = heapless::Vec::new();
+        for command in &state.commands {
+            commands
+                .push(index::Command::new(command.object, command.time.into()))
+                .map_err(|_| too_large.clone())?;
+        }
+
+        let timeout = match &state.timeout {
+            Some(timeout) => {
+                let transition = resolver
+                    .transition(&timeout.transition, &timeout.abort)?
+                    .ok_or(ConfigTextError::InvalidTransition {
+                        line: resolver.line(timeout.time.start()),
+                    })?;
+                Some(index::Timeout::new(
+                    (*timeout.time.get_ref()).into(),
+                    transition,
+                ))
+            }
+            None => None,
+        };
+
+        let mut name = StateName::new();
+        name.push_str(state.name.get_ref())
+            .map_err(|_| ConfigTextError::NameTooLong {
+                name: state.name.get_ref().clone(),
+                line: resolver.line(state.name.start()),
+            })?;
+
+        // The number of states was checked while resolving names
+        if states
+            .push(index::State::new(checks, commands, timeout).with_name(name))
+            .is_err()
+        {
+            unreachable!();
+        }
+    }
+
+    Ok(ConfigFile {
+        default_state: resolver.state(&config.default_state)?,
+        states,
+        hardware: config.hardware,
+    })
+}
+
+impl From<toml::de::Error> for ConfigTextError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigTextError::Syntax(e)
+    }
+}
+
+impl fmt::Display for ConfigTextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigTextError::Syntax(e) => write!(f, "{e}"),
+            ConfigTextError::UnknownState { name, line } => {
+                write!(f, "line {line}: there is no state named `{name}`")
+            }
+            ConfigTextError::DuplicateState { name, line } => {
+                write!(f, "line {line}: there is already a state named `{name}`")
+            }
+            ConfigTextError::NameTooLong { name, line } => {
+                write!(f, "line {line}: the state name `{name}` is too long")
+            }
+            ConfigTextError::InvalidTransition { line } => write!(
+                f,
+                "line {line}: expected exactly one of `transition` or `abort`"
+            ),
+            ConfigTextError::TooLarge { line } => {
+                write!(f, "line {line}: too many states, checks, or commands")
+            }
+            ConfigTextError::UnknownKey { key, line } => {
+                write!(f, "line {line}: unknown key `{key}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigTextError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, ConfigTextError};
+    use crate::index::ConfigFile;
+    use crate::{FloatCondition, PyroContinuityCondition};
+
+    const TEXT: &str = r#"
+default_state = "Poweron"
+
+[[states]]
+name = "Safe"
+
+[[states]]
+name = "Launch"
+
+[[states.checks]]
+# Climbing past 200 m
+object = "Altitude"
+type = "FloatCondition"
+value = { GreaterThan = 200.0 }
+transition = "Safe"
+
+[[states.commands]]
+object = "AnalogOut"
+channel = 0
+value = 1023
+time = { ticks = 500 }
+
+[[states]]
+name = "Poweron"
+
+[[states.checks]]
+object = "Pyro1Continuity"
+value = false
+abort = "Safe"
+persistence = 0.1
+
+[states.timeout]
+time = 1
+transition = "Launch"
+"#;
+
+    #[test]
+    fn test_parse() {
+        let expected = state_machine! {
+            default Poweron;
+
+            state Safe {}
+
+            state Launch {
+                check Altitude(FloatCondition::GreaterThan(200.0)) => transition Safe;
+                command AnalogOut { channel: 0, value: 1023 } after_ticks 500;
+            }
+
+            state Poweron {
+                check Pyro1Continuity(PyroContinuityCondition(false)) held_for 0.1 => abort Safe;
+                timeout after 1.0 => transition Launch;
+            }
+        };
+
+        assert_eq!(parse(TEXT), Ok(expected));
+    }
+
+    /// Parses `text` with the default limits
+    fn parse_default(text: &str) -> Result<ConfigFile, ConfigTextError> {
+        parse(text)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let text = TEXT.replace("abort = \"Safe\"", "abort = \"Sfae\"");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::UnknownState {
+                name: "Sfae".into(),
+                line: 29,
+            })
+        );
+
+        let text = TEXT.replace("name = \"Launch\"", "name = \"Safe\"");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::DuplicateState {
+                name: "Safe".into(),
+                line: 8,
+            })
+        );
+
+        let text = TEXT.replace("\"Launch\"", "\"LaunchDetectAndBoost\"");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::NameTooLong {
+                name: "LaunchDetectAndBoost".into(),
+                line: 8,
+            })
+        );
+
+        let text = TEXT.replace("transition = \"Launch\"", "");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::InvalidTransition { line: 33 })
+        );
+
+        let text = text.replace("time = 1\n", "time = { ticks = 1000 }\n");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::InvalidTransition { line: 33 })
+        );
+
+        let text = TEXT.replace("value = false", "value = 1.0");
+        assert!(matches!(
+            parse_default(&text),
+            Err(ConfigTextError::Syntax(_))
+        ));
+
+        // Poweron is the third state, and Launch has a check
+        assert_eq!(
+            parse::<2, 3, 3>(TEXT),
+            Err(ConfigTextError::TooLarge { line: 24 })
+        );
+        assert_eq!(
+            parse::<3, 0, 3>(TEXT),
+            Err(ConfigTextError::TooLarge { line: 8 })
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        // A misspelled table header would drop the check under it
+        let text = TEXT.replace(
+            "[[states.checks]]\nobject = \"Pyro1",
+            "[[states.check]]\nobject = \"Pyro1",
+        );
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::UnknownKey {
+                key: "check".into(),
+                line: 26,
+            })
+        );
+
+        // A misspelled key would drop the check's transition
+        let text = TEXT.replace("abort = \"Safe\"", "abrot = \"Safe\"");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::UnknownKey {
+                key: "abrot".into(),
+                line: 29,
+            })
+        );
+
+        let text = TEXT.replace("channel = 0", "chanel = 0");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::UnknownKey {
+                key: "chanel".into(),
+                line: 19,
+            })
+        );
+
+        let text = TEXT.replace("transition = \"Launch\"", "transitoin = \"Launch\"");
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::UnknownKey {
+                key: "transitoin".into(),
+                line: 34,
+            })
+        );
+
+        let hardware = "\n[hardware]\npyro_channels = [true, true, true]\nanalog_channels = []\n";
+        assert!(parse_default(&format!("{TEXT}{hardware}")).is_ok());
+        let text = format!(
+            "{TEXT}{}",
+            hardware.replace("analog_channels", "servo_channels")
+        );
+        assert_eq!(
+            parse_default(&text),
+            Err(ConfigTextError::UnknownKey {
+                key: "servo_channels".into(),
+                line: 38,
+            })
+        );
+
+        // Keys inside values are rejected by the value's own syntax
+        let text = TEXT.replace("{ ticks = 500 }", "{ ticks = 500, tick = 1 }");
+        assert!(matches!(
+            parse_default(&text),
+            Err(ConfigTextError::Syntax(_))
+        ));
+    }
+}
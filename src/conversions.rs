@@ -6,15 +6,16 @@ use alloc_traits::{Layout, LocalAlloc, NonZeroLayout};
 use core::mem::{align_of, size_of, MaybeUninit};
 use core::slice;
 
-type State = reference::State<'static>;
+type State<const CHECKS: usize, const COMMANDS: usize> =
+    reference::State<'static, CHECKS, COMMANDS>;
 
-pub fn indices_to_refs(
-    config: &index::ConfigFile,
+pub fn indices_to_refs<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+    config: &index::ConfigFile<STATES, CHECKS, COMMANDS>,
     alloc: &'static dyn LocalAlloc<'static>,
-) -> Option<&'static [State]> {
+) -> Option<&'static [State<CHECKS, COMMANDS>]> {
     let len = config.states.len();
-    let bytes = len * size_of::<State>();
-    let align = align_of::<State>();
+    let bytes = len * size_of::<State<CHECKS, COMMANDS>>();
+    let align = align_of::<State<CHECKS, COMMANDS>>();
 
     // Unwrap always succeeds because align was obtained from `align_of`
     let layout: Layout = alloc::Layout::from_size_align(bytes, align).unwrap().into();
@@ -27,12 +28,12 @@ pub fn indices_to_refs(
     // 3. `mem` is safe for reads up to `bytes` bytes
     // 4. `mem` is only being accessed through this slice, and therefore this mutable reference is
     //    not aliased
-    let uninit: &'static mut [MaybeUninit<State>] =
+    let uninit: &'static mut [MaybeUninit<State<CHECKS, COMMANDS>>] =
         unsafe { slice::from_raw_parts_mut(mem.ptr.as_ptr() as *mut _, len) };
 
     // Create a new, initialized State at each position in the slice
-    for (i, state) in uninit.iter_mut().enumerate() {
-        *state = MaybeUninit::new(State::new(i as u8));
+    for (i, (state, idx_state)) in uninit.iter_mut().zip(&config.states).enumerate() {
+        *state = MaybeUninit::new(State::new(i as u8, idx_state.name.clone()));
     }
 
     // # SAFETY: All of the slice's MaybeUninit<T> are initialized from the for loop above.
@@ -49,7 +50,8 @@ pub fn indices_to_refs(
         // `slice` is initialized, and`MaybeUninit` is guaranteed to have the same layout as `T`.
         // The pointer obtained is valid since it refers to memory owned by `uninit` which is a
         // reference and thus guaranteed to be valid for reads.
-        &*(uninit as *const [MaybeUninit<State>] as *const [State])
+        &*(uninit as *const [MaybeUninit<State<CHECKS, COMMANDS>>]
+            as *const [State<CHECKS, COMMANDS>])
     };
 
     // Now that each state is initialized, we can add the proper checks, commands, and timeouts
@@ -63,11 +65,15 @@ pub fn indices_to_refs(
                 .map(|t| transition_index_to_ref(t, init));
 
             // Create and add the check
-            let ref_check = Check::new(check.data, transition);
+            let ref_check = Check {
+                data: check.data,
+                transition,
+                persistence: check.persistence,
+            };
             let ref_check = alloc_struct(ref_check, alloc).unwrap();
             if ref_state.checks.push(ref_check).is_err() {
                 // The size of `index::State::checks` and `reference::State::checks` is determined
-                // by the same constant, so it is impossible to for one vector to have more
+                // by the same parameter, so it is impossible to for one vector to have more
                 // elements than the capacity of the other
                 unreachable!("State checks exceeded maxmimum number of checks allowed");
             }
@@ -77,7 +83,7 @@ pub fn indices_to_refs(
             let ref_command = alloc_struct(command_index_to_ref(command), alloc).unwrap();
             if ref_state.commands.push(ref_command).is_err() {
                 // The size of `index::State::commands` and `reference::State::commands` is determined
-                // by the same constant, so it is impossible to for one vector to have more
+                // by the same parameter, so it is impossible to for one vector to have more
                 // elements than the capacity of the other
                 unreachable!("State commands exceeded maxmimum number of commands allowed");
             }
@@ -93,14 +99,105 @@ pub fn indices_to_refs(
     Some(init)
 }
 
+/// Converts runtime states back into a config file, so that the flight computer can report the
+/// configuration it is running
+///
+/// Each state's `id` is used as its index, as assigned by [`indices_to_refs`]. The runtime states
+/// don't record the board they were configured for, so the result has no hardware table. Returns
+/// `None` if there are too many states, or if `default_state` or any transition refers to a state
+/// that isn't at its `id` in `states`, such as a state outside of `states` or one that has been
+/// moved.
+pub fn refs_to_indices<'s, const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+    states: &[reference::State<'s, CHECKS, COMMANDS>],
+    default_state: &reference::State<'s, CHECKS, COMMANDS>,
+) -> Option<index::ConfigFile<STATES, CHECKS, COMMANDS>> {
+    let mut config = index::ConfigFile {
+        default_state: state_ref_to_index(default_state, states)?,
+        states: heapless::Vec::new(),
+        hardware: None,
+    };
+
+    for state in states {
+        let mut checks = heapless::Vec::new();
+        for check in state.checks.iter() {
+            let transition = match check.transition {
+                Some(t) => Some(transition_ref_to_index(t, states)?),
+                None => None,
+            };
+            let idx_check = index::Check {
+                data: check.data,
+                transition,
+                persistence: check.persistence,
+            };
+            // Both check vectors have a capacity of `CHECKS`
+            if checks.push(idx_check).is_err() {
+                unreachable!();
+            }
+        }
+
+        let mut commands = heapless::Vec::new();
+        for command in state.commands.iter() {
+            // Both command vectors have a capacity of `COMMANDS`
+            if commands
+                .push(index::Command::new(command.object, command.delay))
+                .is_err()
+            {
+                unreachable!();
+            }
+        }
+
+        let timeout = match state.timeout.get() {
+            Some(t) => Some(index::Timeout::new(
+                t.time,
+                transition_ref_to_index(t.transition, states)?,
+            )),
+            None => None,
+        };
+
+        let mut idx_state = index::State::new(checks, commands, timeout);
+        idx_state.name = state.name.clone();
+        config.states.push(idx_state).ok()?;
+    }
+
+    Some(config)
+}
+
+fn state_ref_to_index<'s, const CHECKS: usize, const COMMANDS: usize>(
+    state: &reference::State<'s, CHECKS, COMMANDS>,
+    states: &[reference::State<'s, CHECKS, COMMANDS>],
+) -> Option<index::StateIndex> {
+    // The `id` is only a valid index if `states` is laid out the way `indices_to_refs` left it
+    let at_id = states.get(state.id as usize)?;
+    if core::ptr::eq(at_id, state) {
+        // # SAFETY: The index was just checked to refer to one of `states`
+        Some(unsafe { index::StateIndex::new_unchecked(state.id) })
+    } else {
+        None
+    }
+}
+
+fn transition_ref_to_index<'s, const CHECKS: usize, const COMMANDS: usize>(
+    transition: reference::StateTransition<'s, CHECKS, COMMANDS>,
+    states: &[reference::State<'s, CHECKS, COMMANDS>],
+) -> Option<index::StateTransition> {
+    Some(match transition {
+        reference::StateTransition::Transition(s) => {
+            index::StateTransition::Transition(state_ref_to_index(s, states)?)
+        }
+        reference::StateTransition::Abort(s) => {
+            index::StateTransition::Abort(state_ref_to_index(s, states)?)
+        }
+    })
+}
+
 fn command_index_to_ref(command: &index::Command) -> reference::Command {
     reference::Command::new(command.object, command.delay)
 }
 
-fn transition_index_to_ref<'s>(
+fn transition_index_to_ref<'s, const CHECKS: usize, const COMMANDS: usize>(
     transition: &index::StateTransition,
-    ref_states: &'s [reference::State<'s>],
-) -> reference::StateTransition<'s> {
+    ref_states: &'s [reference::State<'s, CHECKS, COMMANDS>],
+) -> reference::StateTransition<'s, CHECKS, COMMANDS> {
     match transition {
         index::StateTransition::Transition(s) => {
             let dest_state = ref_states.get::<usize>((*s).into()).unwrap();
@@ -132,12 +229,15 @@ fn alloc_struct<T>(obj: T, alloc: &'static dyn LocalAlloc<'static>) -> Option<&'
 mod tests {
     use crate::{
         index::{Check, Command, ConfigFile, State, StateIndex, StateTransition, Timeout},
-        indices_to_refs, CheckData, CommandObject, FloatCondition, NativeFlagCondition,
-        PyroContinuityCondition, Seconds, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE, MAX_STATES,
+        indices_to_refs, refs_to_indices, CheckData, CommandObject, Delay, FloatCondition,
+        NativeFlagCondition, PyroContinuityCondition, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE,
+        MAX_STATES,
     };
     use heapless::Vec;
     use static_alloc::Bump;
 
+    type RefState<'s> = crate::reference::State<'s, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE>;
+
     const STATE_SIZE: usize = core::mem::size_of::<crate::reference::State>() * MAX_STATES;
     const CHECK_SIZE: usize =
         core::mem::size_of::<crate::reference::Check>() * MAX_CHECKS_PER_STATE * MAX_STATES;
@@ -155,7 +255,7 @@ mod tests {
         // [[states]]
         // name = "Safe"
         //
-        let safe = State::new(Vec::new(), Vec::new(), None);
+        let safe = State::new(Vec::new(), Vec::new(), None).with_name("Safe".into());
         states.push(safe).unwrap();
         // # SAFETY: We just pushed `safe`
         let safe_idx = unsafe { StateIndex::new_unchecked(states.len() as u8 - 1) };
@@ -171,7 +271,10 @@ mod tests {
         //
         let mut descent_commands = Vec::new();
         descent_commands
-            .push(Command::new(CommandObject::DataRate(20), Seconds(0.0)))
+            .push(Command::new(
+                CommandObject::DataRate(20),
+                Delay::Seconds(0.0),
+            ))
             .unwrap();
         let descent = State::new(Vec::new(), descent_commands, None);
         states.push(descent).unwrap();
@@ -234,6 +337,7 @@ mod tests {
         // type = "PyroContinuityCondition"
         // value = false
         // abort = "Safe"
+        // persistence = 0.1
         //
         // [[states.checks]]
         // name = "Pyro2Check"
@@ -251,10 +355,13 @@ mod tests {
         //
         let mut poweron_checks = Vec::new();
         poweron_checks
-            .push(Check::new(
-                CheckData::Pyro1Continuity(PyroContinuityCondition(false)),
-                Some(StateTransition::Abort(safe_idx)),
-            ))
+            .push(
+                Check::new(
+                    CheckData::Pyro1Continuity(PyroContinuityCondition(false)),
+                    Some(StateTransition::Abort(safe_idx)),
+                )
+                .with_persistence(Delay::Seconds(0.1)),
+            )
             .unwrap();
         poweron_checks
             .push(Check::new(
@@ -271,27 +378,39 @@ mod tests {
         let poweron = State::new(
             poweron_checks,
             Vec::new(),
-            Some(Timeout::new(1.0, StateTransition::Transition(launch_idx))),
+            Some(Timeout::new(
+                Delay::Seconds(1.0),
+                StateTransition::Transition(launch_idx),
+            )),
         );
         states.push(poweron).unwrap();
         // # SAFETY: We just pushed `poweron`
         let poweron_idx = unsafe { StateIndex::new_unchecked(states.len() as u8 - 1) };
 
-        let config = ConfigFile {
+        let config: ConfigFile = ConfigFile {
             default_state: poweron_idx,
             states: states.clone(),
+            hardware: None,
         };
 
         let reference_cfg = indices_to_refs(&config, &A).unwrap();
 
+        let default_state = &reference_cfg[usize::from(poweron_idx)];
+        assert_eq!(
+            refs_to_indices(reference_cfg, default_state),
+            Some(config.clone())
+        );
+
         // Test to see if the "reference states" match the "index states" in every way
         for (i, (state, idx_state)) in reference_cfg.iter().zip(states.iter()).enumerate() {
             assert_eq!(state.id, i as u8);
+            assert_eq!(state.name, idx_state.name);
             assert_eq!(state.checks.len(), idx_state.checks.len());
             assert_eq!(state.commands.len(), idx_state.commands.len());
 
             for (check, idx_check) in state.checks.iter().zip(idx_state.checks.iter()) {
                 assert_eq!(check.data, idx_check.data);
+                assert_eq!(check.persistence, idx_check.persistence);
 
                 assert_eq!(check.transition.is_some(), idx_check.transition.is_some());
 
@@ -325,4 +444,38 @@ mod tests {
             }
         }
     }
+
+    /// Makes the state at `from` time out into the state at `to`
+    fn set_timeout<'s>(states: &'s [RefState<'s>], from: usize, to: usize) {
+        let transition = crate::reference::StateTransition::Transition(&states[to]);
+        let timeout = crate::reference::Timeout::new(Delay::Seconds(1.0), transition);
+        states[from].timeout.set(Some(timeout));
+    }
+
+    #[test]
+    fn test_refs_to_indices_checks_positions() {
+        // Laid out the way `indices_to_refs` leaves them
+        let states = [RefState::new(0, None), RefState::new(1, None)];
+        set_timeout(&states, 0, 1);
+        let config: ConfigFile = refs_to_indices(&states, &states[0]).unwrap();
+        assert_eq!(usize::from(config.default_state), 0);
+        match config.states[0].timeout.unwrap().transition {
+            StateTransition::Transition(idx) => assert_eq!(usize::from(idx), 1),
+            StateTransition::Abort(_) => panic!(),
+        }
+
+        // A slice that starts part way through, so the ids are past its end or off by one
+        let tail: Option<ConfigFile> = refs_to_indices(&states[1..], &states[1]);
+        assert!(tail.is_none());
+
+        // The same states in the opposite order, so each id points at the other state
+        let reordered = [RefState::new(1, None), RefState::new(0, None)];
+        set_timeout(&reordered, 1, 0);
+        let config: Option<ConfigFile> = refs_to_indices(&reordered, &reordered[1]);
+        assert!(config.is_none());
+
+        // Even with a valid default state, the transition is still checked
+        let config: Option<ConfigFile> = refs_to_indices(&reordered, &reordered[0]);
+        assert!(config.is_none());
+    }
 }
@@ -0,0 +1,192 @@
+//! Compact numeric error codes shared by the flight computer and the ground station.
+//!
+//! Codes are `u16`s whose high byte identifies the [`Subsystem`] and whose low byte identifies the
+//! error within it. The flight computer reports them in log events and as beep patterns without
+//! needing any strings, and the ground station turns them back into descriptions.
+
+use crate::board::BoardError;
+use crate::image::PatchError;
+use crate::index::{HardwareError, InvalidGeofence};
+use crate::migration::MigrationError;
+
+/// The part of the flight computer an [`ErrorCode`] comes from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Subsystem {
+    Flash = 1,
+    Sensors = 2,
+    Config = 3,
+    Link = 4,
+}
+
+macro_rules! error_codes {
+    ($($subsystem:ident {
+        $($name:ident = $code:literal => $description:literal,)*
+    })*) => {
+        /// An error reported by the flight computer
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        #[repr(u16)]
+        pub enum ErrorCode {
+            $($(
+                #[doc = $description]
+                $name = $code,
+            )*)*
+        }
+
+        // Fail the build if a code's high byte doesn't match the subsystem it is listed under
+        const _: () = {
+            $($(assert!(
+                $code >> 8 == Subsystem::$subsystem as u16,
+                concat!(stringify!($name), " is not numbered within its subsystem")
+            );)*)*
+        };
+
+        impl ErrorCode {
+            /// Returns the error with the numeric code `code`, if there is one
+            pub const fn from_code(code: u16) -> Option<Self> {
+                match code {
+                    $($($code => Some(ErrorCode::$name),)*)*
+                    _ => None,
+                }
+            }
+
+            /// Returns the subsystem this error comes from
+            pub const fn subsystem(self) -> Subsystem {
+                match self {
+                    $($(ErrorCode::$name)|* => Subsystem::$subsystem,)*
+                }
+            }
+
+            /// Returns a human readable description of this error
+            #[cfg(feature = "std")]
+            pub const fn description(self) -> &'static str {
+                match self {
+                    $($(ErrorCode::$name => $description,)*)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    Flash {
+        FlashWriteFailed = 0x0101 => "A page could not be written to flash",
+        FlashEraseFailed = 0x0102 => "A flash block could not be erased",
+        FlashReadFailed = 0x0103 => "A page could not be read from flash",
+        FlashFull = 0x0104 => "The flash is full and logging has stopped",
+    }
+    Sensors {
+        BarometerNotResponding = 0x0201 => "The barometer did not respond",
+        BarometerCalibrationInvalid = 0x0202 => "The barometer's calibration PROM failed its CRC",
+        HighGAccelerometerNotResponding = 0x0203 => "The high-G accelerometer did not respond",
+        LowGAccelerometerNotResponding = 0x0204 => "The low-G accelerometer did not respond",
+        GyroscopeNotResponding = 0x0205 => "The gyroscope did not respond",
+    }
+    Config {
+        ConfigMissing = 0x0301 => "No config is stored in flash",
+        ConfigCorrupt = 0x0302 => "The stored config could not be decoded",
+        ConfigUnsupportedVersion = 0x0303
+            => "The stored config is for an unsupported format version",
+        ConfigHardwareMismatch = 0x0304 => "The config was written for a different board",
+        ConfigMissingChannel = 0x0305 => "The config uses a channel this board does not have",
+        ConfigAnalogOutOfRange = 0x0306
+            => "The config writes a value outside an analog channel's range",
+        ConfigPatchRejected = 0x0307
+            => "An uplinked config patch did not produce the expected image",
+        ConfigInvalidGeofence = 0x0308
+            => "A geofence check's maximum downrange distance is invalid",
+    }
+    Link {
+        UplinkStale = 0x0401 => "No valid uplink packet has been received within the timeout",
+        UplinkBadFrame = 0x0402 => "An uplink frame failed its integrity check",
+    }
+}
+
+impl ErrorCode {
+    /// Returns the numeric code of this error
+    pub const fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Returns the beep pattern for this error as a number of long beeps identifying the
+    /// subsystem, followed by a number of short beeps identifying the error within it
+    pub const fn beeps(self) -> (u8, u8) {
+        (self.subsystem() as u8, self.code() as u8)
+    }
+}
+
+impl From<MigrationError> for ErrorCode {
+    fn from(e: MigrationError) -> Self {
+        match e {
+            MigrationError::UnsupportedVersion(_) => ErrorCode::ConfigUnsupportedVersion,
+            MigrationError::Decode => ErrorCode::ConfigCorrupt,
+        }
+    }
+}
+
+impl From<PatchError> for ErrorCode {
+    fn from(_: PatchError) -> Self {
+        ErrorCode::ConfigPatchRejected
+    }
+}
+
+impl From<InvalidGeofence> for ErrorCode {
+    fn from(_: InvalidGeofence) -> Self {
+        ErrorCode::ConfigInvalidGeofence
+    }
+}
+
+impl From<HardwareError> for ErrorCode {
+    fn from(e: HardwareError) -> Self {
+        match e {
+            HardwareError::MissingPyro { .. } | HardwareError::MissingAnalog { .. } => {
+                ErrorCode::ConfigMissingChannel
+            }
+            HardwareError::AnalogOutOfRange { .. } => ErrorCode::ConfigAnalogOutOfRange,
+        }
+    }
+}
+
+impl From<BoardError> for ErrorCode {
+    fn from(e: BoardError) -> Self {
+        match e {
+            BoardError::HardwareMismatch | BoardError::TooManyAnalogChannels => {
+                ErrorCode::ConfigHardwareMismatch
+            }
+            BoardError::Hardware(e) => e.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorCode, Subsystem};
+
+    #[test]
+    fn test_codes_round_trip() {
+        for code in 0..=u16::MAX {
+            if let Some(error) = ErrorCode::from_code(code) {
+                assert_eq!(error.code(), code);
+                assert_eq!(error.subsystem() as u16, code >> 8);
+                assert!(error.beeps().1 > 0);
+            }
+        }
+        assert_eq!(ErrorCode::from_code(0), None);
+    }
+
+    #[test]
+    fn test_subsystem_and_beeps() {
+        assert_eq!(ErrorCode::FlashFull.subsystem(), Subsystem::Flash);
+        assert_eq!(ErrorCode::UplinkBadFrame.subsystem(), Subsystem::Link);
+        assert_eq!(ErrorCode::ConfigCorrupt.beeps(), (3, 2));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_description() {
+        assert_eq!(
+            ErrorCode::ConfigMissing.description(),
+            "No config is stored in flash"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Estimates of the vehicle's motion derived from raw sensor readings, which checks are evaluated
+//! against.
+
+use crate::Seconds;
+
+/// Standard gravity in m/s², for converting accelerations to g
+pub const STANDARD_GRAVITY: f32 = 9.806_65;
+
+/// Estimates vertical velocity by differentiating barometric altitude
+///
+/// Differencing raw altitude samples amplifies the barometer's noise, so this is an alpha-beta
+/// filter: it predicts the next altitude from the current velocity, and corrects both by a fraction
+/// of the difference from the measured altitude. Smaller gains give a smoother but slower estimate.
+/// The flight computer evaluates
+/// [`CheckData::VerticalVelocity`](crate::CheckData::VerticalVelocity) checks against
+/// [`VelocityEstimator::velocity`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VelocityEstimator {
+    /// The fraction of the altitude error applied to the altitude
+    alpha: f32,
+    /// The fraction of the altitude error, per second, applied to the velocity
+    beta: f32,
+    altitude: f32,
+    velocity: Option<f32>,
+    last_sample: Option<Seconds>,
+}
+
+impl VelocityEstimator {
+    /// Creates an estimator with the given filter gains, which must be between 0 and 1 and are
+    /// typically around 0.1 for `alpha` and 0.005 for `beta`
+    pub const fn new(alpha: f32, beta: f32) -> Self {
+        Self {
+            alpha,
+            beta,
+            altitude: 0.0,
+            velocity: None,
+            last_sample: None,
+        }
+    }
+
+    /// Feeds in an `altitude` in metres measured at time `now`
+    ///
+    /// Samples that aren't later than the previous one are ignored.
+    pub fn update(&mut self, altitude: f32, now: Seconds) {
+        let last = match self.last_sample {
+            Some(last) => last,
+            None => {
+                self.altitude = altitude;
+                self.last_sample = Some(now);
+                return;
+            }
+        };
+        let dt = now.0 - last.0;
+        if dt <= 0.0 {
+            return;
+        }
+
+        let velocity = self.velocity.unwrap_or(0.0);
+        let predicted = self.altitude + velocity * dt;
+        let error = altitude - predicted;
+        self.altitude = predicted + self.alpha * error;
+        self.velocity = Some(velocity + self.beta * error / dt);
+        self.last_sample = Some(now);
+    }
+
+    /// Returns the filtered altitude in metres, or `None` if no samples have been fed in
+    pub fn altitude(&self) -> Option<f32> {
+        self.last_sample.map(|_| self.altitude)
+    }
+
+    /// Returns the vertical velocity in m/s, positive upwards, or `None` until two samples have
+    /// been fed in
+    pub fn velocity(&self) -> Option<f32> {
+        self.velocity
+    }
+}
+
+/// Returns the magnitude in g of an acceleration measured along three axes in m/s²
+///
+/// The flight computer evaluates [`CheckData::Acceleration`](crate::CheckData::Acceleration) checks
+/// against the magnitude of the high-G accelerometer's reading, so a launch is detected the same
+/// way whichever way the board is mounted.
+pub fn acceleration_magnitude(acceleration: [f32; 3]) -> f32 {
+    let [x, y, z] = acceleration;
+    libm::sqrtf(x * x + y * y + z * z) / STANDARD_GRAVITY
+}
+
+/// Estimates the vehicle's angle off vertical by integrating gyro rates
+///
+/// The orientation is kept as a unit quaternion, so that rolling about the vehicle's long axis
+/// doesn't disturb the estimate the way it would with Euler angles. Gyros only measure changes in
+/// orientation, so the estimator must be created while the vehicle is vertical on the pad. The
+/// flight computer evaluates [`CheckData::TiltAngle`](crate::CheckData::TiltAngle) checks against
+/// [`TiltEstimator::tilt`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TiltEstimator {
+    /// The rotation from the vehicle's frame to the pad's, as `[w, x, y, z]`
+    orientation: [f32; 4],
+}
+
+impl TiltEstimator {
+    /// Creates an estimator for a vehicle that is currently vertical
+    pub const fn new() -> Self {
+        Self {
+            orientation: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Integrates the gyro `rates` in degrees per second over the `dt` since the last update
+    ///
+    /// The rates are about the vehicle's x, y, and z axes, where z is the long axis pointing out
+    /// of the nose.
+    pub fn update(&mut self, rates: [f32; 3], dt: Seconds) {
+        let [x, y, z] = rates.map(f32::to_radians);
+        let rate = libm::sqrtf(x * x + y * y + z * z);
+        let angle = rate * dt.0;
+        if angle == 0.0 {
+            return;
+        }
+
+        // The rotation over this step, about the axis of the rates
+        let (sin, cos) = (libm::sinf(angle / 2.0), libm::cosf(angle / 2.0));
+        let step = [cos, x / rate * sin, y / rate * sin, z / rate * sin];
+
+        let [aw, ax, ay, az] = self.orientation;
+        let [bw, bx, by, bz] = step;
+        let product = [
+            aw * bw - ax * bx - ay * by - az * bz,
+            aw * bx + ax * bw + ay * bz - az * by,
+            aw * by - ax * bz + ay * bw + az * bx,
+            aw * bz + ax * by - ay * bx + az * bw,
+        ];
+
+        // Renormalize so that rounding errors don't build up over a flight
+        let norm = libm::sqrtf(product.iter().map(|c| c * c).sum());
+        self.orientation = product.map(|c| c / norm);
+    }
+
+    /// Returns the angle between the vehicle's long axis and vertical in degrees
+    pub fn tilt(&self) -> f32 {
+        let [_, x, y, _] = self.orientation;
+        // The vertical component of the long axis, after rotating it into the pad's frame
+        let vertical = 1.0 - 2.0 * (x * x + y * y);
+        libm::acosf(vertical.clamp(-1.0, 1.0)).to_degrees()
+    }
+}
+
+impl Default for TiltEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acceleration_magnitude, TiltEstimator, VelocityEstimator, STANDARD_GRAVITY};
+    use crate::Seconds;
+
+    #[test]
+    fn test_velocity_estimator() {
+        let mut estimator = VelocityEstimator::new(0.1, 0.005);
+        assert_eq!(estimator.velocity(), None);
+
+        estimator.update(100.0, Seconds(0.0));
+        assert_eq!(estimator.altitude(), Some(100.0));
+        assert_eq!(estimator.velocity(), None);
+
+        // Climbing at 50 m/s, sampled at 100 Hz
+        for i in 1..=1000 {
+            let t = i as f32 / 100.0;
+            estimator.update(100.0 + 50.0 * t, Seconds(t));
+        }
+        let velocity = estimator.velocity().unwrap();
+        assert!((velocity - 50.0).abs() < 0.5, "{velocity}");
+
+        // A stale sample is ignored
+        let before = estimator;
+        estimator.update(0.0, Seconds(5.0));
+        assert_eq!(estimator, before);
+    }
+
+    #[test]
+    fn test_velocity_estimator_rejects_noise() {
+        let mut estimator = VelocityEstimator::new(0.1, 0.005);
+
+        // Stationary, with ±2 m of noise on every sample
+        for i in 0..1000 {
+            let noise = if i % 2 == 0 { 2.0 } else { -2.0 };
+            estimator.update(noise, Seconds(i as f32 / 100.0));
+        }
+        let velocity = estimator.velocity().unwrap();
+        assert!(velocity.abs() < 1.0, "{velocity}");
+    }
+
+    #[test]
+    fn test_acceleration_magnitude() {
+        assert_eq!(acceleration_magnitude([0.0, 0.0, STANDARD_GRAVITY]), 1.0);
+        assert_eq!(acceleration_magnitude([0.0, -STANDARD_GRAVITY, 0.0]), 1.0);
+        assert_eq!(acceleration_magnitude([0.0; 3]), 0.0);
+
+        // 3 g and 4 g on two axes
+        let g = STANDARD_GRAVITY;
+        let magnitude = acceleration_magnitude([3.0 * g, 0.0, 4.0 * g]);
+        assert!((magnitude - 5.0).abs() < 1e-5, "{magnitude}");
+    }
+
+    /// Feeds `rates` to `estimator` at 100 Hz for `seconds`
+    fn rotate(estimator: &mut TiltEstimator, rates: [f32; 3], seconds: u32) {
+        for _ in 0..seconds * 100 {
+            estimator.update(rates, Seconds(0.01));
+        }
+    }
+
+    #[test]
+    fn test_tilt_estimator() {
+        let mut estimator = TiltEstimator::new();
+        assert_eq!(estimator.tilt(), 0.0);
+
+        // Pitch over by 30 degrees
+        rotate(&mut estimator, [10.0, 0.0, 0.0], 3);
+        let tilt = estimator.tilt();
+        assert!((tilt - 30.0).abs() < 0.01, "{tilt}");
+
+        // Rolling about the long axis doesn't change the tilt
+        rotate(&mut estimator, [0.0, 0.0, 90.0], 2);
+        let tilt = estimator.tilt();
+        assert!((tilt - 30.0).abs() < 0.01, "{tilt}");
+
+        // Tilting about a diagonal axis
+        let mut estimator = TiltEstimator::new();
+        let rate = 20.0 / core::f32::consts::SQRT_2;
+        rotate(&mut estimator, [rate, -rate, 0.0], 2);
+        let tilt = estimator.tilt();
+        assert!((tilt - 40.0).abs() < 0.01, "{tilt}");
+
+        // And back
+        rotate(&mut estimator, [-rate, rate, 0.0], 2);
+        assert!(estimator.tilt() < 0.1);
+    }
+}
@@ -113,7 +113,7 @@ impl<T: StableDeref, const N: usize> FrozenVec<T, N> {
     }
 
     /// Returns an iterator over the vector.
-    pub fn iter(&self) -> Iter<T, N> {
+    pub fn iter(&self) -> Iter<'_, T, N> {
         self.into_iter()
     }
 
@@ -226,7 +226,7 @@ fn test_accessors() {
     let y = 2;
     let z = 4;
 
-    assert_eq!(vec.is_empty(), true);
+    assert!(vec.is_empty());
     assert_eq!(vec.len(), 0);
     // assert_eq!(vec.first(), None);
     // assert_eq!(vec.last(), None);
@@ -236,7 +236,7 @@ fn test_accessors() {
     vec.push(&y).unwrap();
     vec.push(&z).unwrap();
 
-    assert_eq!(vec.is_empty(), false);
+    assert!(!vec.is_empty());
     assert_eq!(vec.len(), 3);
     // assert_eq!(vec.first(), Some("a"));
     // assert_eq!(vec.last(), Some("c"));
@@ -0,0 +1,498 @@
+//! Serde implementations for [`CheckData`] and [`CommandObject`] that use the config file syntax
+//! in human readable formats like TOML or JSON.
+//!
+//! ```toml
+//! [[states.checks]]
+//! object = "Altitude"
+//! type = "FloatCondition"
+//! value = { GreaterThan = 200.0 }
+//!
+//! [[states.commands]]
+//! object = "DataRate"
+//! value = 20
+//! ```
+//!
+//! Binary formats such as postcard are unaffected and keep using the compact derived encoding.
+
+use crate::{
+    CheckData, CommandObject, FloatCondition, GeofenceCondition, NativeFlagCondition,
+    PyroContinuityCondition,
+};
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The derived (binary) representation of [`CheckData`]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CheckData", rename = "CheckData")]
+enum CheckDataDef {
+    Altitude(FloatCondition),
+    ApogeeFlag(NativeFlagCondition),
+    Pyro1Continuity(PyroContinuityCondition),
+    Pyro2Continuity(PyroContinuityCondition),
+    Pyro3Continuity(PyroContinuityCondition),
+    UplinkStale(NativeFlagCondition),
+    VerticalVelocity(FloatCondition),
+    Acceleration(FloatCondition),
+    TiltAngle(FloatCondition),
+    Geofence(GeofenceCondition),
+}
+
+/// The derived (binary) representation of [`CommandObject`]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CommandObject", rename = "CommandObject")]
+enum CommandObjectDef {
+    Pyro1(bool),
+    Pyro2(bool),
+    Pyro3(bool),
+    Beacon(bool),
+    DataRate(u16),
+    AnalogOut { channel: u8, value: u16 },
+}
+
+/// The object that a check reads
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub(crate) enum CheckObject {
+    Altitude,
+    ApogeeFlag,
+    Pyro1Continuity,
+    Pyro2Continuity,
+    Pyro3Continuity,
+    UplinkStale,
+    VerticalVelocity,
+    Acceleration,
+    TiltAngle,
+    Geofence,
+}
+
+/// The kind of condition a check's value describes
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub(crate) enum ConditionType {
+    FloatCondition,
+    Flag,
+    PyroContinuityCondition,
+    GeofenceCondition,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum CheckValue {
+    Flag(bool),
+    Float(FloatCondition),
+    Geofence(GeofenceCondition),
+}
+
+/// The human readable representation of [`CheckData`]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "CheckData")]
+pub(crate) struct CheckDataRepr {
+    pub object: CheckObject,
+    /// Optional when deserializing, because it is implied by `object`
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<ConditionType>,
+    pub value: CheckValue,
+}
+
+impl From<CheckData> for CheckDataRepr {
+    fn from(data: CheckData) -> Self {
+        let (object, condition, value) = match data {
+            CheckData::Altitude(c) => (
+                CheckObject::Altitude,
+                ConditionType::FloatCondition,
+                CheckValue::Float(c),
+            ),
+            CheckData::ApogeeFlag(c) => (
+                CheckObject::ApogeeFlag,
+                ConditionType::Flag,
+                CheckValue::Flag(c.0),
+            ),
+            CheckData::Pyro1Continuity(c) => (
+                CheckObject::Pyro1Continuity,
+                ConditionType::PyroContinuityCondition,
+                CheckValue::Flag(c.0),
+            ),
+            CheckData::Pyro2Continuity(c) => (
+                CheckObject::Pyro2Continuity,
+                ConditionType::PyroContinuityCondition,
+                CheckValue::Flag(c.0),
+            ),
+            CheckData::Pyro3Continuity(c) => (
+                CheckObject::Pyro3Continuity,
+                ConditionType::PyroContinuityCondition,
+                CheckValue::Flag(c.0),
+            ),
+            CheckData::UplinkStale(c) => (
+                CheckObject::UplinkStale,
+                ConditionType::Flag,
+                CheckValue::Flag(c.0),
+            ),
+            CheckData::VerticalVelocity(c) => (
+                CheckObject::VerticalVelocity,
+                ConditionType::FloatCondition,
+                CheckValue::Float(c),
+            ),
+            CheckData::Acceleration(c) => (
+                CheckObject::Acceleration,
+                ConditionType::FloatCondition,
+                CheckValue::Float(c),
+            ),
+            CheckData::TiltAngle(c) => (
+                CheckObject::TiltAngle,
+                ConditionType::FloatCondition,
+                CheckValue::Float(c),
+            ),
+            CheckData::Geofence(c) => (
+                CheckObject::Geofence,
+                ConditionType::GeofenceCondition,
+                CheckValue::Geofence(c),
+            ),
+        };
+
+        Self {
+            object,
+            condition: Some(condition),
+            value,
+        }
+    }
+}
+
+impl TryFrom<CheckDataRepr> for CheckData {
+    type Error = &'static str;
+
+    fn try_from(repr: CheckDataRepr) -> Result<Self, Self::Error> {
+        let (data, condition) = match (repr.object, repr.value) {
+            (CheckObject::Altitude, CheckValue::Float(c)) => {
+                (CheckData::Altitude(c), ConditionType::FloatCondition)
+            }
+            (CheckObject::ApogeeFlag, CheckValue::Flag(b)) => (
+                CheckData::ApogeeFlag(NativeFlagCondition(b)),
+                ConditionType::Flag,
+            ),
+            (CheckObject::Pyro1Continuity, CheckValue::Flag(b)) => (
+                CheckData::Pyro1Continuity(PyroContinuityCondition(b)),
+                ConditionType::PyroContinuityCondition,
+            ),
+            (CheckObject::Pyro2Continuity, CheckValue::Flag(b)) => (
+                CheckData::Pyro2Continuity(PyroContinuityCondition(b)),
+                ConditionType::PyroContinuityCondition,
+            ),
+            (CheckObject::Pyro3Continuity, CheckValue::Flag(b)) => (
+                CheckData::Pyro3Continuity(PyroContinuityCondition(b)),
+                ConditionType::PyroContinuityCondition,
+            ),
+            (CheckObject::UplinkStale, CheckValue::Flag(b)) => (
+                CheckData::UplinkStale(NativeFlagCondition(b)),
+                ConditionType::Flag,
+            ),
+            (CheckObject::VerticalVelocity, CheckValue::Float(c)) => (
+                CheckData::VerticalVelocity(c),
+                ConditionType::FloatCondition,
+            ),
+            (CheckObject::Acceleration, CheckValue::Float(c)) => {
+                (CheckData::Acceleration(c), ConditionType::FloatCondition)
+            }
+            (CheckObject::TiltAngle, CheckValue::Float(c)) => {
+                (CheckData::TiltAngle(c), ConditionType::FloatCondition)
+            }
+            (CheckObject::Geofence, CheckValue::Geofence(c)) => {
+                (CheckData::Geofence(c), ConditionType::GeofenceCondition)
+            }
+            (CheckObject::Altitude, _) => return Err("`Altitude` expects a FloatCondition value"),
+            (CheckObject::VerticalVelocity, _) => {
+                return Err("`VerticalVelocity` expects a FloatCondition value")
+            }
+            (CheckObject::Acceleration, _) => {
+                return Err("`Acceleration` expects a FloatCondition value")
+            }
+            (CheckObject::TiltAngle, _) => {
+                return Err("`TiltAngle` expects a FloatCondition value")
+            }
+            (CheckObject::Geofence, _) => {
+                return Err("`Geofence` expects a GeofenceCondition value")
+            }
+            (_, _) => return Err("Continuity and flag checks expect a boolean value"),
+        };
+
+        match repr.condition {
+            Some(c) if c != condition => Err("`type` does not match the check's object"),
+            _ => Ok(data),
+        }
+    }
+}
+
+/// The object that a command acts upon
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub(crate) enum CommandTarget {
+    Pyro1,
+    Pyro2,
+    Pyro3,
+    Beacon,
+    DataRate,
+    AnalogOut,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum CommandValue {
+    Flag(bool),
+    Short(u16),
+}
+
+/// The human readable representation of [`CommandObject`]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "CommandObject")]
+pub(crate) struct CommandObjectRepr {
+    pub object: CommandTarget,
+    /// Only present for `AnalogOut`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+    pub value: CommandValue,
+}
+
+impl From<CommandObject> for CommandObjectRepr {
+    fn from(object: CommandObject) -> Self {
+        let (object, channel, value) = match object {
+            CommandObject::Pyro1(b) => (CommandTarget::Pyro1, None, CommandValue::Flag(b)),
+            CommandObject::Pyro2(b) => (CommandTarget::Pyro2, None, CommandValue::Flag(b)),
+            CommandObject::Pyro3(b) => (CommandTarget::Pyro3, None, CommandValue::Flag(b)),
+            CommandObject::Beacon(b) => (CommandTarget::Beacon, None, CommandValue::Flag(b)),
+            CommandObject::DataRate(r) => (CommandTarget::DataRate, None, CommandValue::Short(r)),
+            CommandObject::AnalogOut { channel, value } => (
+                CommandTarget::AnalogOut,
+                Some(channel),
+                CommandValue::Short(value),
+            ),
+        };
+
+        Self {
+            object,
+            channel,
+            value,
+        }
+    }
+}
+
+impl TryFrom<CommandObjectRepr> for CommandObject {
+    type Error = &'static str;
+
+    fn try_from(repr: CommandObjectRepr) -> Result<Self, Self::Error> {
+        match (repr.object, repr.channel, repr.value) {
+            (CommandTarget::AnalogOut, Some(channel), CommandValue::Short(value)) => {
+                Ok(CommandObject::AnalogOut { channel, value })
+            }
+            (CommandTarget::AnalogOut, None, _) => Err("`AnalogOut` requires a `channel`"),
+            (CommandTarget::AnalogOut, _, _) => Err("`AnalogOut` expects an integer value"),
+            (_, Some(_), _) => Err("Only `AnalogOut` commands have a `channel`"),
+            (CommandTarget::Pyro1, None, CommandValue::Flag(b)) => Ok(CommandObject::Pyro1(b)),
+            (CommandTarget::Pyro2, None, CommandValue::Flag(b)) => Ok(CommandObject::Pyro2(b)),
+            (CommandTarget::Pyro3, None, CommandValue::Flag(b)) => Ok(CommandObject::Pyro3(b)),
+            (CommandTarget::Beacon, None, CommandValue::Flag(b)) => Ok(CommandObject::Beacon(b)),
+            (CommandTarget::DataRate, None, CommandValue::Short(r)) => {
+                Ok(CommandObject::DataRate(r))
+            }
+            (CommandTarget::DataRate, _, _) => Err("`DataRate` expects an integer value"),
+            (_, _, _) => Err("Pyro and beacon commands expect a boolean value"),
+        }
+    }
+}
+
+impl Serialize for CheckData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            CheckDataRepr::from(*self).serialize(serializer)
+        } else {
+            CheckDataDef::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CheckData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            CheckDataRepr::deserialize(deserializer)?
+                .try_into()
+                .map_err(D::Error::custom)
+        } else {
+            CheckDataDef::deserialize(deserializer)
+        }
+    }
+}
+
+impl Serialize for CommandObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            CommandObjectRepr::from(*self).serialize(serializer)
+        } else {
+            CommandObjectDef::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CommandObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            CommandObjectRepr::deserialize(deserializer)?
+                .try_into()
+                .map_err(D::Error::custom)
+        } else {
+            CommandObjectDef::deserialize(deserializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CheckData, CommandObject, FloatCondition, GeofenceCondition, NativeFlagCondition,
+        PyroContinuityCondition,
+    };
+
+    #[test]
+    fn test_check_data_toml() {
+        let data: CheckData = toml::from_str(
+            r#"
+            object = "Altitude"
+            type = "FloatCondition"
+            value = { GreaterThan = 200.0 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            CheckData::Altitude(FloatCondition::GreaterThan(200.0))
+        );
+
+        // `type` is optional
+        let data: CheckData = toml::from_str(
+            r#"
+            object = "Pyro1Continuity"
+            value = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            CheckData::Pyro1Continuity(PyroContinuityCondition(false))
+        );
+
+        let data = CheckData::ApogeeFlag(NativeFlagCondition(true));
+        let text = toml::to_string(&data).unwrap();
+        assert_eq!(
+            text,
+            "object = \"ApogeeFlag\"\ntype = \"Flag\"\nvalue = true\n"
+        );
+        assert_eq!(toml::from_str::<CheckData>(&text).unwrap(), data);
+    }
+
+    #[test]
+    fn test_check_data_toml_mismatch() {
+        let wrong_value = toml::from_str::<CheckData>(
+            r#"
+            object = "Altitude"
+            value = true
+            "#,
+        );
+        assert!(wrong_value.is_err());
+
+        let wrong_type = toml::from_str::<CheckData>(
+            r#"
+            object = "ApogeeFlag"
+            type = "PyroContinuityCondition"
+            value = true
+            "#,
+        );
+        assert!(wrong_type.is_err());
+    }
+
+    #[test]
+    fn test_float_checks_toml() {
+        let checks = [
+            (
+                "VerticalVelocity",
+                CheckData::VerticalVelocity(FloatCondition::LessThan(0.0)),
+            ),
+            (
+                "Acceleration",
+                CheckData::Acceleration(FloatCondition::LessThan(0.0)),
+            ),
+            (
+                "TiltAngle",
+                CheckData::TiltAngle(FloatCondition::LessThan(0.0)),
+            ),
+        ];
+        for (object, data) in checks {
+            let text = alloc::format!("object = \"{object}\"\nvalue = {{ LessThan = 0.0 }}\n");
+            assert_eq!(toml::from_str::<CheckData>(&text).unwrap(), data);
+
+            let wrong_value = alloc::format!("object = \"{object}\"\nvalue = true\n");
+            assert!(toml::from_str::<CheckData>(&wrong_value).is_err());
+        }
+    }
+
+    #[test]
+    fn test_geofence_toml() {
+        let data: CheckData = toml::from_str(
+            r#"
+            object = "Geofence"
+            value = { max_downrange = 3000.0 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            CheckData::Geofence(GeofenceCondition {
+                max_downrange: 3000.0
+            })
+        );
+
+        assert!(toml::from_str::<CheckData>("object = \"Geofence\"\nvalue = true\n").is_err());
+    }
+
+    #[test]
+    fn test_command_object_toml() {
+        let object: CommandObject = toml::from_str(
+            r#"
+            object = "DataRate"
+            value = 20
+            "#,
+        )
+        .unwrap();
+        assert_eq!(object, CommandObject::DataRate(20));
+
+        let object = CommandObject::Pyro2(true);
+        let text = toml::to_string(&object).unwrap();
+        assert_eq!(text, "object = \"Pyro2\"\nvalue = true\n");
+        assert_eq!(toml::from_str::<CommandObject>(&text).unwrap(), object);
+
+        assert!(toml::from_str::<CommandObject>("object = \"Beacon\"\nvalue = 1\n").is_err());
+
+        let object = CommandObject::AnalogOut {
+            channel: 2,
+            value: 512,
+        };
+        let text = toml::to_string(&object).unwrap();
+        assert_eq!(text, "object = \"AnalogOut\"\nchannel = 2\nvalue = 512\n");
+        assert_eq!(toml::from_str::<CommandObject>(&text).unwrap(), object);
+
+        assert!(toml::from_str::<CommandObject>("object = \"AnalogOut\"\nvalue = 1\n").is_err());
+        assert!(
+            toml::from_str::<CommandObject>("object = \"Pyro1\"\nchannel = 1\nvalue = true\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_binary_encoding_unchanged() {
+        // Binary formats keep the derived, externally tagged encoding
+        let data = CheckData::Pyro2Continuity(PyroContinuityCondition(true));
+        assert_eq!(postcard::to_allocvec(&data).unwrap(), [3, 1]);
+
+        let object = CommandObject::DataRate(300);
+        let bytes = postcard::to_allocvec(&object).unwrap();
+        assert_eq!(bytes, [4, 0xAC, 0x02]);
+        assert_eq!(
+            postcard::from_bytes::<CommandObject>(&bytes).unwrap(),
+            object
+        );
+    }
+}
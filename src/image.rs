@@ -0,0 +1,345 @@
+//! Serialized config images and the delta patches used to update them over the uplink.
+//!
+//! A [`DeltaPatch`] records only the byte ranges that differ between two images, so a threshold
+//! tweak costs a few bytes rather than the whole config. The patch carries the length and CRC of
+//! the image it produces, and [`ConfigImage::apply`] refuses to return an image that doesn't match
+//! them, so a patch applied to the wrong base image is never activated.
+//!
+//! An image starts with the version of the config format it was written with, followed by the
+//! postcard encoding of the config. The version is covered by the CRC and by patches like the rest
+//! of the image, and an image written by older software is upgraded when it is decoded.
+
+use crate::checksum::{Checksum, Crc32Mpeg2};
+use crate::index::ConfigFile;
+use crate::max_size::MaxSize;
+use crate::migration::{self, MigrationError, CONFIG_FORMAT_VERSION};
+use crate::MAX_CONFIG_SIZE;
+
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of changed byte ranges in a [`DeltaPatch`]
+pub const MAX_PATCH_RUNS: usize = 32;
+
+/// The maximum number of changed bytes carried by a [`DeltaPatch`]
+pub const MAX_PATCH_BYTES: usize = 256;
+
+/// Unchanged gaps this short or shorter are folded into the surrounding runs, since a new run
+/// costs more to encode than the bytes it would skip
+const RUN_MERGE_GAP: usize = 4;
+
+/// The number of bytes at the start of an image before the encoded config, which hold its format
+/// version
+pub const HEADER_SIZE: usize = 1;
+
+// Images are padded to a whole number of words for the CRC, which must not run past the buffer
+const _: () = assert!(ConfigImage::MAX_SIZE.is_multiple_of(4));
+
+/// A config file as it is stored in flash
+#[derive(Clone)]
+pub struct ConfigImage {
+    len: usize,
+    /// The image, followed by zeros up to `MAX_SIZE`
+    bytes: [u8; ConfigImage::MAX_SIZE],
+}
+
+/// A single range of bytes that differs between two images
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub struct PatchRun {
+    pub offset: u16,
+    pub len: u16,
+}
+
+/// The changes needed to turn one [`ConfigImage`] into another
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DeltaPatch {
+    /// The length of the patched image
+    pub len: u16,
+    /// The CRC of the patched image, as computed by [`ConfigImage::crc`]
+    pub crc: u32,
+    pub runs: Vec<PatchRun, MAX_PATCH_RUNS>,
+    /// The new contents of every run, concatenated in order
+    pub data: Vec<u8, MAX_PATCH_BYTES>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// A run lies outside of the patched image, or the runs don't add up to the data
+    OutOfBounds,
+    /// The patched image doesn't match the CRC in the patch
+    CrcMismatch,
+}
+
+impl ConfigImage {
+    /// The largest image that can be stored, which is the size of its flash region
+    ///
+    /// Buffers that hold a whole image, such as the one an image is read back into from flash,
+    /// should be this long.
+    pub const MAX_SIZE: usize = MAX_CONFIG_SIZE;
+
+    /// Serializes `config` into an image
+    ///
+    /// Fails to build if the largest config with the same limits as `config` doesn't fit in
+    /// [`MAX_SIZE`](Self::MAX_SIZE).
+    pub fn new<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+        config: &ConfigFile<STATES, CHECKS, COMMANDS>,
+    ) -> Self {
+        const {
+            assert!(
+                HEADER_SIZE + <ConfigFile<STATES, CHECKS, COMMANDS> as MaxSize>::MAX_SIZE
+                    <= Self::MAX_SIZE,
+                "The largest config file with these limits does not fit in a config image"
+            )
+        };
+
+        let mut bytes = [0; Self::MAX_SIZE];
+        bytes[0] = CONFIG_FORMAT_VERSION;
+        // The config's maximum size was asserted above
+        let len = match postcard::to_slice(config, &mut bytes[HEADER_SIZE..]) {
+            Ok(used) => HEADER_SIZE + used.len(),
+            Err(_) => unreachable!(),
+        };
+        Self { len, bytes }
+    }
+
+    /// Copies an image read back from flash, returning `None` if it is too large to be one
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut image = Self {
+            len: bytes.len(),
+            bytes: [0; Self::MAX_SIZE],
+        };
+        image.bytes.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        Some(image)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the version of the config format this image was written with, or `None` if the
+    /// image is empty
+    pub fn version(&self) -> Option<u8> {
+        self.as_bytes().first().copied()
+    }
+
+    /// Computes the CRC of this image
+    ///
+    /// This is the CRC-32/MPEG-2 of the image padded with zeros to a multiple of four bytes. It is
+    /// what the STM32 CRC peripheral computes when fed the padded image as big-endian words, as
+    /// described in the [`checksum`](crate::checksum) module.
+    pub fn crc(&self) -> u32 {
+        let padded_len = self.len.next_multiple_of(4);
+        Crc32Mpeg2::checksum(&self.bytes[..padded_len])
+    }
+
+    /// Decodes the config file stored in this image, upgrading it from the version it was written
+    /// with
+    pub fn decode<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+        &self,
+    ) -> Result<ConfigFile<STATES, CHECKS, COMMANDS>, MigrationError> {
+        match self.as_bytes().split_first() {
+            Some((&version, config)) => migration::migrate(config, version),
+            None => Err(MigrationError::Decode),
+        }
+    }
+
+    /// Computes the patch that turns `old` into `new`, returning `None` if the images differ by
+    /// too much to fit in a [`DeltaPatch`], in which case the whole image should be sent instead
+    pub fn delta(old: &Self, new: &Self) -> Option<DeltaPatch> {
+        let mut patch = DeltaPatch {
+            len: new.len as u16,
+            crc: new.crc(),
+            runs: Vec::new(),
+            data: Vec::new(),
+        };
+
+        let new_bytes = new.as_bytes();
+        let differs = |i: usize| old.as_bytes().get(i) != Some(&new_bytes[i]);
+
+        // The start and end of the run currently being extended
+        let mut run: Option<(usize, usize)> = None;
+        for i in (0..new_bytes.len()).filter(|&i| differs(i)) {
+            run = match run {
+                Some((start, end)) if i - end <= RUN_MERGE_GAP => Some((start, i + 1)),
+                Some((start, end)) => {
+                    patch.push_run(start, &new_bytes[start..end])?;
+                    Some((i, i + 1))
+                }
+                None => Some((i, i + 1)),
+            };
+        }
+        if let Some((start, end)) = run {
+            patch.push_run(start, &new_bytes[start..end])?;
+        }
+
+        Some(patch)
+    }
+
+    /// Applies `patch` to this image, returning the patched image only if it matches the length and
+    /// CRC the patch was made for
+    pub fn apply(&self, patch: &DeltaPatch) -> Result<Self, PatchError> {
+        let len = patch.len as usize;
+        if len > Self::MAX_SIZE {
+            return Err(PatchError::OutOfBounds);
+        }
+
+        let mut image = Self {
+            len,
+            bytes: [0; Self::MAX_SIZE],
+        };
+        let kept = self.len.min(len);
+        image.bytes[..kept].copy_from_slice(&self.bytes[..kept]);
+
+        let mut data = patch.data.as_slice();
+        for run in &patch.runs {
+            let (offset, run_len) = (run.offset as usize, run.len as usize);
+            if offset + run_len > len || run_len > data.len() {
+                return Err(PatchError::OutOfBounds);
+            }
+            let (run_data, rest) = data.split_at(run_len);
+            image.bytes[offset..offset + run_len].copy_from_slice(run_data);
+            data = rest;
+        }
+        if !data.is_empty() {
+            return Err(PatchError::OutOfBounds);
+        }
+
+        if image.crc() != patch.crc {
+            return Err(PatchError::CrcMismatch);
+        }
+        Ok(image)
+    }
+}
+
+impl DeltaPatch {
+    fn push_run(&mut self, offset: usize, bytes: &[u8]) -> Option<()> {
+        let run = PatchRun {
+            offset: offset as u16,
+            len: bytes.len() as u16,
+        };
+        self.runs.push(run).ok()?;
+        self.data.extend_from_slice(bytes).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigImage, PatchError};
+    use crate::checksum::tests::stm32_crc;
+    use crate::index::{Check, ConfigFile, State, StateIndex, StateTransition};
+    use crate::migration::{MigrationError, CONFIG_FORMAT_VERSION};
+    use crate::{CheckData, FloatCondition};
+    use heapless::Vec;
+
+    fn config(threshold: f32, states: u8) -> ConfigFile {
+        // # SAFETY: Every state refers to state 0, which always exists
+        let first = unsafe { StateIndex::new_unchecked(0) };
+        let mut config = ConfigFile {
+            default_state: first,
+            states: Vec::new(),
+            hardware: None,
+        };
+        for _ in 0..states {
+            let mut checks = Vec::new();
+            checks
+                .push(Check::new(
+                    CheckData::Altitude(FloatCondition::GreaterThan(threshold)),
+                    Some(StateTransition::Transition(first)),
+                ))
+                .unwrap();
+            config
+                .states
+                .push(State::new(checks, Vec::new(), None))
+                .unwrap();
+        }
+        config
+    }
+
+    #[test]
+    fn test_threshold_tweak() {
+        let old = ConfigImage::new(&config(200.0, 8));
+        let new = ConfigImage::new(&config(250.0, 8));
+
+        let patch = ConfigImage::delta(&old, &new).unwrap();
+        assert_eq!(patch.runs.len(), 8);
+        assert!(patch.data.len() <= 8 * 4);
+
+        let patched = old.apply(&patch).unwrap();
+        assert_eq!(patched.as_bytes(), new.as_bytes());
+        assert_eq!(patched.decode().unwrap(), config(250.0, 8));
+    }
+
+    #[test]
+    fn test_resize() {
+        let small = ConfigImage::new(&config(200.0, 2));
+        let large = ConfigImage::new(&config(200.0, 4));
+
+        let grow = ConfigImage::delta(&small, &large).unwrap();
+        assert_eq!(small.apply(&grow).unwrap().as_bytes(), large.as_bytes());
+
+        let shrink = ConfigImage::delta(&large, &small).unwrap();
+        assert_eq!(large.apply(&shrink).unwrap().as_bytes(), small.as_bytes());
+    }
+
+    #[test]
+    fn test_wrong_base_rejected() {
+        let old = ConfigImage::new(&config(200.0, 2));
+        let new = ConfigImage::new(&config(250.0, 2));
+
+        let patch = ConfigImage::delta(&old, &new).unwrap();
+
+        // Differs from `old` outside of the patched runs
+        let mut bytes = old.as_bytes().to_vec();
+        bytes[0] ^= 1;
+        let other = ConfigImage::from_bytes(&bytes).unwrap();
+        assert_eq!(other.apply(&patch).err(), Some(PatchError::CrcMismatch));
+
+        let mut bad = patch.clone();
+        bad.runs[0].offset = bad.len;
+        assert_eq!(old.apply(&bad).err(), Some(PatchError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_version_stored() {
+        let image = ConfigImage::new(&config(200.0, 1));
+        assert_eq!(image.version(), Some(CONFIG_FORMAT_VERSION));
+        assert_eq!(image.as_bytes()[0], CONFIG_FORMAT_VERSION);
+
+        // Written as the original format, which had no hardware table
+        let old = ConfigImage::from_bytes(&[1, 0, 0]).unwrap();
+        let upgraded = config(200.0, 0);
+        assert_eq!(old.decode(), Ok(upgraded.clone()));
+
+        // The same bytes aren't a valid config of the current format
+        let current = ConfigImage::from_bytes(&[CONFIG_FORMAT_VERSION, 0, 0]).unwrap();
+        let result: Result<ConfigFile, _> = current.decode();
+        assert_eq!(result, Err(MigrationError::Decode));
+
+        // Rewriting the image in the current format patches the version too
+        let new = ConfigImage::new(&upgraded);
+        let patch = ConfigImage::delta(&old, &new).unwrap();
+        assert_eq!(patch.runs[0].offset, 0);
+        assert_eq!(old.apply(&patch).unwrap().as_bytes(), new.as_bytes());
+
+        let empty = ConfigImage::from_bytes(&[]).unwrap();
+        let result: Result<ConfigFile, _> = empty.decode();
+        assert_eq!(result, Err(MigrationError::Decode));
+    }
+
+    #[test]
+    fn test_crc_matches_stm32() {
+        for states in 0..4 {
+            let image = ConfigImage::new(&config(200.0, states));
+
+            let mut bytes = image.as_bytes().to_vec();
+            while !bytes.len().is_multiple_of(4) {
+                bytes.push(0);
+            }
+            let words = bytes.chunks_exact(4).map(|word| {
+                let word = [word[0], word[1], word[2], word[3]];
+                u32::from_be_bytes(word)
+            });
+            assert_eq!(image.crc(), stm32_crc(words));
+        }
+    }
+}
@@ -1,15 +1,221 @@
 //! State machine data structures that use indices to reference state transitions.
 //! This is needed when the config file is serialized between the verifier and the flight computer.
 
-use crate::{MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE, MAX_STATES};
+use crate::{
+    CheckData, CommandObject, MAX_ANALOG_CHANNELS, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE,
+    MAX_STATES, MAX_STATE_NAME_LEN, PYRO_CHANNELS,
+};
 
 use heapless::Vec;
 use serde::{Deserialize, Serialize};
 
+/// A state machine config
+///
+/// The const parameters limit the number of states, and the number of checks and commands in each
+/// state. They don't affect the encoding, so a config can be decoded with any limits it fits in.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct ConfigFile {
+pub struct ConfigFile<
+    const STATES: usize = MAX_STATES,
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
     pub default_state: StateIndex,
-    pub states: Vec<State, MAX_STATES>,
+    pub states: Vec<State<CHECKS, COMMANDS>, STATES>,
+    /// The channels of the board this config was written for. Configs without a hardware table
+    /// are not checked against the board they run on
+    pub hardware: Option<HardwareTable>,
+}
+
+impl<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>
+    ConfigFile<STATES, CHECKS, COMMANDS>
+{
+    /// Moves this config into one with different limits, returning `None` if it has more states,
+    /// checks, or commands than they allow
+    pub fn with_limits<
+        const NEW_STATES: usize,
+        const NEW_CHECKS: usize,
+        const NEW_COMMANDS: usize,
+    >(
+        self,
+    ) -> Option<ConfigFile<NEW_STATES, NEW_CHECKS, NEW_COMMANDS>> {
+        let mut states = Vec::new();
+        for state in self.states {
+            states.push(state.with_limits()?).ok()?;
+        }
+
+        Some(ConfigFile {
+            default_state: self.default_state,
+            states,
+            hardware: self.hardware,
+        })
+    }
+
+    /// Checks that every pyro and analog channel referenced by this config's checks and commands
+    /// exists in its hardware table, and that analog values are within their channel's range
+    ///
+    /// Returns the first problem found. Configs without a hardware table always pass.
+    pub fn check_hardware(&self) -> Result<(), HardwareError> {
+        match &self.hardware {
+            Some(hardware) => self.check_channels(hardware),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks every channel referenced by this config against `hardware`, regardless of this
+    /// config's own hardware table
+    pub(crate) fn check_channels(&self, hardware: &HardwareTable) -> Result<(), HardwareError> {
+        for (i, state) in self.states.iter().enumerate() {
+            // # SAFETY: `i` is the index of `state` within this config
+            let state_idx = unsafe { StateIndex::new_unchecked(i as u8) };
+
+            for check in state.checks.iter() {
+                let pyro = match check.data {
+                    CheckData::Pyro1Continuity(_) => 1,
+                    CheckData::Pyro2Continuity(_) => 2,
+                    CheckData::Pyro3Continuity(_) => 3,
+                    _ => continue,
+                };
+                if !hardware.has_pyro(pyro) {
+                    return Err(HardwareError::MissingPyro {
+                        state: state_idx,
+                        pyro,
+                    });
+                }
+            }
+
+            for command in state.commands.iter() {
+                let pyro = match command.object {
+                    CommandObject::Pyro1(_) => 1,
+                    CommandObject::Pyro2(_) => 2,
+                    CommandObject::Pyro3(_) => 3,
+                    CommandObject::AnalogOut { channel, value } => {
+                        let analog = hardware.analog_channel(channel).ok_or(
+                            HardwareError::MissingAnalog {
+                                state: state_idx,
+                                channel,
+                            },
+                        )?;
+                        if value < analog.min || value > analog.max {
+                            return Err(HardwareError::AnalogOutOfRange {
+                                state: state_idx,
+                                channel,
+                                value,
+                            });
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                if !hardware.has_pyro(pyro) {
+                    return Err(HardwareError::MissingPyro {
+                        state: state_idx,
+                        pyro,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if this config may run on a board with the channels in `board`
+    ///
+    /// This is the case if the config has no hardware table, or if its table describes the same
+    /// channels as `board`, in any order
+    pub fn matches_hardware(&self, board: &HardwareTable) -> bool {
+        match &self.hardware {
+            Some(hardware) => hardware.same_channels(board),
+            None => true,
+        }
+    }
+
+    /// Checks that every geofence check in this config describes a usable area
+    ///
+    /// Returns the first state with an invalid geofence.
+    pub fn check_geofences(&self) -> Result<(), InvalidGeofence> {
+        for (i, state) in self.states.iter().enumerate() {
+            for check in state.checks.iter() {
+                if let CheckData::Geofence(geofence) = check.data {
+                    if !geofence.is_valid() {
+                        // # SAFETY: `i` is the index of `state` within this config
+                        let state = unsafe { StateIndex::new_unchecked(i as u8) };
+                        return Err(InvalidGeofence { state });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes which output channels exist on a board
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HardwareTable {
+    /// Whether Pyro1, Pyro2, and Pyro3 are populated, in that order
+    pub pyro_channels: [bool; PYRO_CHANNELS],
+    /// The analog outputs (DAC/PWM) that exist, which servos are also driven by
+    pub analog_channels: Vec<AnalogChannel, MAX_ANALOG_CHANNELS>,
+}
+
+impl HardwareTable {
+    /// Returns true if the pyro channel numbered `pyro` (starting from 1) exists
+    pub fn has_pyro(&self, pyro: u8) -> bool {
+        match (pyro as usize).checked_sub(1) {
+            Some(i) => self.pyro_channels.get(i).copied().unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Returns the analog output numbered `channel`, if it exists
+    pub fn analog_channel(&self, channel: u8) -> Option<&AnalogChannel> {
+        self.analog_channels.iter().find(|a| a.channel == channel)
+    }
+
+    /// Returns true if `other` has the same pyro channels and the same analog channels, which
+    /// may be listed in a different order
+    pub fn same_channels(&self, other: &HardwareTable) -> bool {
+        let contains_all = |a: &Self, b: &Self| {
+            a.analog_channels
+                .iter()
+                .all(|channel| b.analog_channels.contains(channel))
+        };
+        self.pyro_channels == other.pyro_channels
+            && contains_all(self, other)
+            && contains_all(other, self)
+    }
+}
+
+/// An analog output channel and the range of values it accepts
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct AnalogChannel {
+    /// The channel number used by [`CommandObject::AnalogOut`]
+    pub channel: u8,
+    /// The smallest value that may be written to this channel
+    pub min: u16,
+    /// The largest value that may be written to this channel
+    pub max: u16,
+}
+
+/// Reasons a config is rejected by [`ConfigFile::check_hardware`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HardwareError {
+    /// A check or command in `state` uses a pyro channel the board doesn't have
+    MissingPyro { state: StateIndex, pyro: u8 },
+    /// A command in `state` writes to an analog channel the board doesn't have
+    MissingAnalog { state: StateIndex, channel: u8 },
+    /// A command in `state` writes a value outside of the channel's range
+    AnalogOutOfRange {
+        state: StateIndex,
+        channel: u8,
+        value: u16,
+    },
+}
+
+/// A geofence check in `state` whose maximum downrange distance is not finite and positive, as
+/// returned by [`ConfigFile::check_geofences`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidGeofence {
+    pub state: StateIndex,
 }
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
@@ -30,7 +236,7 @@ impl StateIndex {
     /// This wrapper simply allows us to feel better about unwrapping `get()`s that use index at
     /// other places in the codebase because we assume constructing an invalid `StateIndex` is
     /// impossible
-    pub unsafe fn new_unchecked(index: u8) -> Self {
+    pub const unsafe fn new_unchecked(index: u8) -> Self {
         StateIndex(index)
     }
 }
@@ -41,42 +247,68 @@ impl From<StateIndex> for usize {
     }
 }
 
+/// The name of a [`State`]
+pub type StateName = heapless::String<MAX_STATE_NAME_LEN>;
+
 /// A state that the rocket/flight computer can be in
 ///
 /// This should be things like Armed, Stage1, Stage2, Safe, etc.
 ///
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct State {
-    //pub name: String<16>,
-    pub checks: Vec<Check, MAX_CHECKS_PER_STATE>,
-    pub commands: Vec<Command, MAX_COMMANDS_PER_STATE>,
+pub struct State<
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
+    /// A name for displaying this state to people. It has no effect on how the state is run.
+    pub name: Option<StateName>,
+    pub checks: Vec<Check, CHECKS>,
+    pub commands: Vec<Command, COMMANDS>,
     pub timeout: Option<Timeout>,
 }
 
-impl State {
-    pub fn new(
-        checks: Vec<Check, MAX_CHECKS_PER_STATE>,
-        commands: Vec<Command, MAX_COMMANDS_PER_STATE>,
+impl<const CHECKS: usize, const COMMANDS: usize> State<CHECKS, COMMANDS> {
+    pub const fn new(
+        checks: Vec<Check, CHECKS>,
+        commands: Vec<Command, COMMANDS>,
         timeout: Option<Timeout>,
     ) -> Self {
         Self {
+            name: None,
             checks,
             commands,
             timeout,
         }
     }
+
+    pub fn with_name(mut self, name: StateName) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Moves this state into one with different limits, returning `None` if it has more checks or
+    /// commands than they allow
+    pub fn with_limits<const NEW_CHECKS: usize, const NEW_COMMANDS: usize>(
+        self,
+    ) -> Option<State<NEW_CHECKS, NEW_COMMANDS>> {
+        Some(State {
+            name: self.name,
+            checks: Vec::from_slice(&self.checks).ok()?,
+            commands: Vec::from_slice(&self.commands).ok()?,
+            timeout: self.timeout,
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
 pub struct Timeout {
-    /// Time in seconds to wait before transitioning
-    pub time: f32,
+    /// Time to wait before transitioning
+    pub time: crate::Delay,
     /// The transition that is made when the state times out
     pub transition: StateTransition,
 }
 
 impl Timeout {
-    pub fn new(time: f32, transition: StateTransition) -> Self {
+    pub const fn new(time: crate::Delay, transition: StateTransition) -> Self {
         Self { time, transition }
     }
 }
@@ -87,11 +319,25 @@ pub struct Check {
     //pub name: String<16>,
     pub data: crate::CheckData,
     pub transition: Option<StateTransition>,
+    /// How long the condition must hold continuously before the transition is made. If `None`,
+    /// the transition is made as soon as the condition holds.
+    pub persistence: Option<crate::Delay>,
 }
 
 impl Check {
-    pub fn new(data: crate::CheckData, transition: Option<StateTransition>) -> Self {
-        Self { data, transition }
+    pub const fn new(data: crate::CheckData, transition: Option<StateTransition>) -> Self {
+        Self {
+            data,
+            transition,
+            persistence: None,
+        }
+    }
+
+    /// Requires the condition to hold continuously for `persistence` before the transition is
+    /// made, so that single-sample glitches don't cause transitions
+    pub const fn with_persistence(mut self, persistence: crate::Delay) -> Self {
+        self.persistence = Some(persistence);
+        self
     }
 }
 
@@ -114,20 +360,150 @@ pub struct Command {
     pub object: crate::CommandObject,
 
     /// How long after the state activates to execute this command
-    pub delay: crate::Seconds,
+    pub delay: crate::Delay,
 }
 
 impl Command {
-    pub fn new(object: crate::CommandObject, delay: crate::Seconds) -> Self {
+    pub const fn new(object: crate::CommandObject, delay: crate::Delay) -> Self {
         Self { object, delay }
     }
 }
 
+// Fail the build if any of the constructors can no longer be used in const contexts
+const _: Check = Check::new(
+    CheckData::ApogeeFlag(crate::NativeFlagCondition(true)),
+    None,
+)
+.with_persistence(crate::Delay::Ticks(1));
+const _: Command = Command::new(CommandObject::Beacon(true), crate::Delay::Seconds(0.0));
+const _: State = State::new(
+    Vec::new(),
+    Vec::new(),
+    Some(Timeout::new(
+        crate::Delay::Seconds(1.0),
+        // # SAFETY: This index is never used
+        StateTransition::Transition(unsafe { StateIndex::new_unchecked(0) }),
+    )),
+);
+
 #[cfg(test)]
 mod tests {
+    use super::{
+        AnalogChannel, Check, Command, ConfigFile, HardwareError, HardwareTable, InvalidGeofence,
+        State, StateIndex,
+    };
+    use crate::{CheckData, CommandObject, Delay, GeofenceCondition};
+    use heapless::Vec;
+
+    fn config_with_command(object: CommandObject) -> ConfigFile {
+        let mut commands = Vec::new();
+        commands
+            .push(Command::new(object, Delay::Seconds(0.0)))
+            .unwrap();
+
+        let mut states = Vec::new();
+        states.push(State::new(Vec::new(), commands, None)).unwrap();
+
+        let mut analog_channels = Vec::new();
+        analog_channels
+            .push(AnalogChannel {
+                channel: 4,
+                min: 100,
+                max: 200,
+            })
+            .unwrap();
+
+        ConfigFile {
+            // # SAFETY: We just pushed the only state
+            default_state: unsafe { StateIndex::new_unchecked(0) },
+            states,
+            hardware: Some(HardwareTable {
+                pyro_channels: [true, true, false],
+                analog_channels,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_check_hardware() {
+        // # SAFETY: Only used for comparison
+        let state = unsafe { StateIndex::new_unchecked(0) };
+
+        assert_eq!(
+            config_with_command(CommandObject::Pyro2(true)).check_hardware(),
+            Ok(())
+        );
+        assert_eq!(
+            config_with_command(CommandObject::Pyro3(true)).check_hardware(),
+            Err(HardwareError::MissingPyro { state, pyro: 3 })
+        );
+
+        let analog = |channel, value| CommandObject::AnalogOut { channel, value };
+        assert_eq!(config_with_command(analog(4, 150)).check_hardware(), Ok(()));
+        assert_eq!(
+            config_with_command(analog(5, 150)).check_hardware(),
+            Err(HardwareError::MissingAnalog { state, channel: 5 })
+        );
+        assert_eq!(
+            config_with_command(analog(4, 201)).check_hardware(),
+            Err(HardwareError::AnalogOutOfRange {
+                state,
+                channel: 4,
+                value: 201
+            })
+        );
+
+        let mut config = config_with_command(CommandObject::Pyro3(true));
+        config.hardware = None;
+        assert_eq!(config.check_hardware(), Ok(()));
+    }
+
+    #[test]
+    fn test_matches_hardware() {
+        let mut config = config_with_command(CommandObject::Beacon(true));
+        let mut board = config.hardware.clone().unwrap();
+        assert!(config.matches_hardware(&board));
+
+        board.pyro_channels[2] = true;
+        assert!(!config.matches_hardware(&board));
+
+        config.hardware = None;
+        assert!(config.matches_hardware(&board));
+    }
+
+    #[test]
+    fn test_check_geofences() {
+        let mut config = config_with_command(CommandObject::Beacon(true));
+        assert_eq!(config.check_geofences(), Ok(()));
+
+        for (max_downrange, valid) in [(3000.0, true), (0.0, false), (f32::INFINITY, false)] {
+            let data = CheckData::Geofence(GeofenceCondition { max_downrange });
+            config.states[0].checks.clear();
+            config.states[0]
+                .checks
+                .push(Check::new(data, None))
+                .unwrap();
+
+            // # SAFETY: Only used for comparison
+            let state = unsafe { StateIndex::new_unchecked(0) };
+            let expected = if valid {
+                Ok(())
+            } else {
+                Err(InvalidGeofence { state })
+            };
+            assert_eq!(config.check_geofences(), expected);
+        }
+    }
+
     #[test]
-    #[cfg(target_pointer_width = "32")]
-    fn test() {
-        assert_eq!(core::mem::size_of::<crate::index::ConfigFile>(), 1608);
+    fn test_ram_budget() {
+        // The flight computer keeps the decoded config in RAM for the whole flight, so a change
+        // to the size of the config should be deliberate. The lengths of the vectors are `usize`,
+        // so the size on the host differs from the size on the flight computer
+        #[cfg(target_pointer_width = "32")]
+        const SIZE: usize = 2688;
+        #[cfg(target_pointer_width = "64")]
+        const SIZE: usize = 3152;
+        assert_eq!(core::mem::size_of::<ConfigFile>(), SIZE);
     }
 }
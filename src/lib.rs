@@ -2,22 +2,94 @@
 
 extern crate alloc;
 
+#[macro_use]
+mod macros;
+
+pub mod board;
+pub mod checksum;
+#[cfg(feature = "std")]
+pub mod config_text;
 pub mod conversions;
+pub mod errors;
+pub mod estimate;
 pub mod frozen;
+mod human_readable;
+pub mod image;
 pub mod index;
+pub mod link;
+pub mod max_size;
+pub mod migration;
 pub mod reference;
+pub mod ring;
+pub mod units;
+
+pub use conversions::{indices_to_refs, refs_to_indices};
 
-pub use conversions::indices_to_refs;
+#[doc(hidden)]
+pub use heapless;
 
+/// The default limit on the number of states in a config. Firmware can choose other limits through
+/// the const parameters of [`index::ConfigFile`], as long as the largest config still fits in
+/// [`MAX_CONFIG_SIZE`].
 pub const MAX_STATES: usize = 16;
+/// The default limit on the number of checks in a state
 pub const MAX_CHECKS_PER_STATE: usize = 3;
+/// The default limit on the number of commands in a state
 pub const MAX_COMMANDS_PER_STATE: usize = 3;
+/// The maximum length of a state's name in bytes
+pub const MAX_STATE_NAME_LEN: usize = 16;
+/// The maximum number of analog output channels a board's [`index::HardwareTable`] can describe
+pub const MAX_ANALOG_CHANNELS: usize = 8;
+
+/// The number of pyro channels that checks and commands can refer to (Pyro1 through Pyro3)
+pub const PYRO_CHANNELS: usize = 3;
+
+/// The size of the flash region reserved for the serialized config file, which is one page of the
+/// W25N flash
+pub const MAX_CONFIG_SIZE: usize = 2048;
+
+// Fail the build if the largest possible config file cannot be stored in its flash region
+const _: () = assert!(
+    image::HEADER_SIZE + <index::ConfigFile as max_size::MaxSize>::MAX_SIZE <= MAX_CONFIG_SIZE,
+    "The largest possible config file does not fit in MAX_CONFIG_SIZE"
+);
 
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
 pub struct Seconds(pub f32);
 
+/// An amount of time in a config file, such as a command's delay or a state's timeout
+///
+/// Times that need to be exact, like sub-millisecond pyro sequencing, can be given in ticks of the
+/// flight computer's timer to avoid the rounding that comes with `f32` seconds.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum Delay {
+    Seconds(f32),
+    Ticks(u32),
+}
+
+impl Delay {
+    /// Returns this delay in seconds, using the timer's tick rate for delays given in ticks
+    pub fn as_seconds(self, ticks_per_second: u32) -> Seconds {
+        match self {
+            Delay::Seconds(seconds) => Seconds(seconds),
+            Delay::Ticks(ticks) => Seconds(ticks as f32 / ticks_per_second as f32),
+        }
+    }
+
+    /// Returns this delay in ticks of a timer running at `ticks_per_second`
+    ///
+    /// Delays given in seconds are rounded to the nearest tick. Negative delays become 0, and
+    /// delays longer than `u32::MAX` ticks saturate.
+    pub fn as_ticks(self, ticks_per_second: u32) -> u32 {
+        match self {
+            Delay::Seconds(seconds) => (seconds * ticks_per_second as f32 + 0.5) as u32,
+            Delay::Ticks(ticks) => ticks,
+        }
+    }
+}
+
 /// Describes the check for a `native' condition, I.E, a condition that the state machine emulates.
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
 pub struct NativeFlagCondition(pub bool);
@@ -25,20 +97,87 @@ pub struct NativeFlagCondition(pub bool);
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
 pub struct PyroContinuityCondition(pub bool);
 
+/// Describes the approved recovery area for a geofence check
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct GeofenceCondition {
+    /// The maximum horizontal distance from the launch site in metres
+    pub max_downrange: f32,
+}
+
+impl GeofenceCondition {
+    /// Returns whether a vehicle `downrange` metres from the launch site has left the area
+    pub fn is_breached(self, downrange: f32) -> bool {
+        downrange > self.max_downrange
+    }
+
+    /// Returns whether this describes a usable area
+    pub fn is_valid(self) -> bool {
+        self.max_downrange.is_finite() && self.max_downrange > 0.0
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
 pub enum FloatCondition {
     GreaterThan(f32),
     LessThan(f32),
-    Between { upper_bound: f32, lower_bound: f32 },
+    Between {
+        upper_bound: f32,
+        lower_bound: f32,
+    },
+    /// Met once the value rises above `threshold`, and stays met until it falls below
+    /// `threshold - band`
+    GreaterThanWithHysteresis {
+        threshold: f32,
+        band: f32,
+    },
+    /// Met once the value falls below `threshold`, and stays met until it rises above
+    /// `threshold + band`
+    LessThanWithHysteresis {
+        threshold: f32,
+        band: f32,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+impl FloatCondition {
+    /// Returns whether `value` meets this condition, given whether the condition was met by the
+    /// previous value. `was_met` only affects the conditions with hysteresis.
+    pub fn is_met(self, value: f32, was_met: bool) -> bool {
+        match self {
+            FloatCondition::GreaterThan(threshold) => value > threshold,
+            FloatCondition::LessThan(threshold) => value < threshold,
+            FloatCondition::Between {
+                upper_bound,
+                lower_bound,
+            } => lower_bound < value && value < upper_bound,
+            FloatCondition::GreaterThanWithHysteresis { threshold, band } if was_met => {
+                value > threshold - band
+            }
+            FloatCondition::GreaterThanWithHysteresis { threshold, .. } => value > threshold,
+            FloatCondition::LessThanWithHysteresis { threshold, band } if was_met => {
+                value < threshold + band
+            }
+            FloatCondition::LessThanWithHysteresis { threshold, .. } => value < threshold,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CheckData {
     Altitude(FloatCondition),
     ApogeeFlag(NativeFlagCondition),
     Pyro1Continuity(PyroContinuityCondition),
     Pyro2Continuity(PyroContinuityCondition),
     Pyro3Continuity(PyroContinuityCondition),
+    /// Whether the uplink has gone quiet for longer than the [`link::LinkWatchdog`] timeout
+    UplinkStale(NativeFlagCondition),
+    /// Vertical velocity in m/s, positive upwards
+    VerticalVelocity(FloatCondition),
+    /// Total acceleration magnitude in g
+    Acceleration(FloatCondition),
+    /// Angle between the vehicle's axis and vertical in degrees
+    TiltAngle(FloatCondition),
+    /// Whether the vehicle has left the approved recovery area, according to the position estimate
+    Geofence(GeofenceCondition),
 }
 
 /// Represents the state that something's value can be, this can be the value a command will set
@@ -54,11 +193,60 @@ pub enum ObjectState {
 }
 
 /// An object that a command can act upon
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CommandObject {
     Pyro1(bool),
     Pyro2(bool),
     Pyro3(bool),
     Beacon(bool),
     DataRate(u16),
+    /// Sets a generic analog output, such as a DAC level or a PWM duty cycle, on `channel` to
+    /// `value`
+    AnalogOut {
+        channel: u8,
+        value: u16,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Delay, FloatCondition, Seconds};
+
+    #[test]
+    fn test_delay_conversions() {
+        assert_eq!(Delay::Ticks(250).as_seconds(1000), Seconds(0.25));
+        assert_eq!(Delay::Seconds(0.25).as_seconds(1000), Seconds(0.25));
+
+        assert_eq!(Delay::Ticks(7).as_ticks(1000), 7);
+        assert_eq!(Delay::Seconds(0.0016).as_ticks(1000), 2);
+        assert_eq!(Delay::Seconds(0.0014).as_ticks(1000), 1);
+        assert_eq!(Delay::Seconds(-1.0).as_ticks(1000), 0);
+    }
+
+    #[test]
+    fn test_float_condition_hysteresis() {
+        let condition = FloatCondition::GreaterThanWithHysteresis {
+            threshold: 200.0,
+            band: 10.0,
+        };
+
+        let mut met = false;
+        let mut history = [false; 5];
+        for (i, value) in [195.0, 201.0, 195.0, 189.0, 195.0].into_iter().enumerate() {
+            met = condition.is_met(value, met);
+            history[i] = met;
+        }
+        assert_eq!(history, [false, true, true, false, false]);
+
+        let condition = FloatCondition::LessThanWithHysteresis {
+            threshold: 0.0,
+            band: 2.0,
+        };
+        assert!(!condition.is_met(1.0, false));
+        assert!(condition.is_met(1.0, true));
+        assert!(!condition.is_met(3.0, true));
+
+        // Conditions without hysteresis ignore the previous result
+        assert!(!FloatCondition::GreaterThan(200.0).is_met(195.0, true));
+    }
 }
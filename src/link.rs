@@ -0,0 +1,69 @@
+//! Tracking of the health of the command uplink.
+
+use crate::Seconds;
+
+/// Tracks the time since the last valid uplink packet was received
+///
+/// The flight computer feeds this every time it receives a valid packet, and evaluates
+/// [`CheckData::UplinkStale`](crate::CheckData::UplinkStale) checks against
+/// [`LinkWatchdog::is_stale`]. This lets a config refuse to arm, or fall back to autonomous only
+/// behavior, when the command link is lost.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinkWatchdog {
+    timeout: Seconds,
+    last_packet: Option<Seconds>,
+}
+
+impl LinkWatchdog {
+    /// Creates a watchdog that considers the link stale once `timeout` has passed without a valid
+    /// packet. The link is stale until the first packet is received.
+    pub const fn new(timeout: Seconds) -> Self {
+        Self {
+            timeout,
+            last_packet: None,
+        }
+    }
+
+    /// Records that a valid uplink packet was received at time `now`
+    pub fn packet_received(&mut self, now: Seconds) {
+        self.last_packet = Some(now);
+    }
+
+    /// Returns the time elapsed between the last valid packet and `now`, or `None` if no packet
+    /// has been received yet
+    pub fn time_since_last_packet(&self, now: Seconds) -> Option<Seconds> {
+        self.last_packet.map(|last| Seconds(now.0 - last.0))
+    }
+
+    /// Returns true if no valid packet has been received within the timeout before `now`
+    pub fn is_stale(&self, now: Seconds) -> bool {
+        match self.time_since_last_packet(now) {
+            Some(elapsed) => elapsed.0 > self.timeout.0,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinkWatchdog;
+    use crate::Seconds;
+
+    #[test]
+    fn test_link_watchdog() {
+        let mut watchdog = LinkWatchdog::new(Seconds(5.0));
+        assert!(watchdog.is_stale(Seconds(0.0)));
+        assert_eq!(watchdog.time_since_last_packet(Seconds(0.0)), None);
+
+        watchdog.packet_received(Seconds(1.0));
+        assert!(!watchdog.is_stale(Seconds(6.0)));
+        assert!(watchdog.is_stale(Seconds(6.5)));
+        assert_eq!(
+            watchdog.time_since_last_packet(Seconds(6.5)),
+            Some(Seconds(5.5))
+        );
+
+        watchdog.packet_received(Seconds(6.5));
+        assert!(!watchdog.is_stale(Seconds(7.0)));
+    }
+}
@@ -0,0 +1,334 @@
+/// Declares an [`index::ConfigFile`](crate::index::ConfigFile) using a readable DSL
+///
+/// Each state is given a name which is resolved to a [`StateIndex`](crate::index::StateIndex) at
+/// compile time, so referencing a state that doesn't exist, or declaring too many states, checks,
+/// or commands, is a compile error rather than a runtime one. The name is also stored in the
+/// state for display, so it must be at most [`MAX_STATE_NAME_LEN`](crate::MAX_STATE_NAME_LEN)
+/// bytes long. Within a state, checks must come first, then commands, then an optional timeout.
+/// Times are given in seconds with `after`, or in timer ticks with `after_ticks`. A check can
+/// require its condition to hold for some time before transitioning with `held_for` or
+/// `held_for_ticks`.
+///
+/// The config uses the default limits on states, checks, and commands unless it starts with a
+/// line such as `limits 8 states, 4 checks, 3 commands;`, which sets the const parameters of the
+/// [`ConfigFile`](crate::index::ConfigFile) instead.
+///
+/// ```
+/// use nova_software_common::state_machine;
+/// use nova_software_common::{FloatCondition, PyroContinuityCondition};
+///
+/// let config = state_machine! {
+///     default Poweron;
+///
+///     state Safe {}
+///
+///     state Launch {
+///         check Altitude(FloatCondition::GreaterThan(200.0)) => transition Flight;
+///         command Beacon(true) after 0.0;
+///         command AnalogOut { channel: 0, value: 1023 } after_ticks 500;
+///     }
+///
+///     state Flight {}
+///
+///     state Poweron {
+///         check Pyro1Continuity(PyroContinuityCondition(false)) held_for 0.1 => abort Safe;
+///         timeout after 1.0 => transition Launch;
+///     }
+/// };
+///
+/// assert_eq!(config.states.len(), 4);
+/// ```
+#[macro_export]
+macro_rules! state_machine {
+    (
+        $(limits $max_states:tt states, $max_checks:tt checks, $max_commands:tt commands;)?
+        default $default:ident;
+
+        $(
+            state $name:ident {
+                $(check $check:ident $check_arg:tt
+                    $($held_unit:ident $held:expr)?
+                    $(=> $check_kind:ident $check_dest:ident)?;)*
+                $(command $command:ident $command_arg:tt $delay_unit:ident $delay:expr;)*
+                $(timeout $time_unit:ident $time:expr => $timeout_kind:ident $timeout_dest:ident;)?
+            }
+        )*
+    ) => {{
+        $crate::state_machine!(@indices (0) $($name)*);
+
+        const __MAX_STATES: usize =
+            $crate::state_machine!(@limit $($max_states)? $crate::MAX_STATES);
+        const __MAX_CHECKS: usize =
+            $crate::state_machine!(@limit $($max_checks)? $crate::MAX_CHECKS_PER_STATE);
+        const __MAX_COMMANDS: usize =
+            $crate::state_machine!(@limit $($max_commands)? $crate::MAX_COMMANDS_PER_STATE);
+
+        const _: () = assert!(
+            $crate::state_machine!(@count $($name)*) <= __MAX_STATES,
+            "state_machine! declares more states than its limit"
+        );
+
+        let mut states: $crate::heapless::Vec<
+            $crate::index::State<__MAX_CHECKS, __MAX_COMMANDS>,
+            __MAX_STATES,
+        > = $crate::heapless::Vec::new();
+
+        $(
+            const _: () = assert!(
+                stringify!($name).len() <= $crate::MAX_STATE_NAME_LEN,
+                concat!("State name `", stringify!($name), "` is longer than MAX_STATE_NAME_LEN")
+            );
+            const _: () = assert!(
+                $crate::state_machine!(@count $($check)*) <= __MAX_CHECKS,
+                concat!("State `", stringify!($name), "` has more checks than its limit")
+            );
+            const _: () = assert!(
+                $crate::state_machine!(@count $($command)*) <= __MAX_COMMANDS,
+                concat!("State `", stringify!($name), "` has more commands than its limit")
+            );
+
+            #[allow(unused_mut)]
+            let mut checks = $crate::heapless::Vec::new();
+            $(
+                let check = $crate::index::Check {
+                    data: $crate::CheckData::$check $check_arg,
+                    transition: $crate::state_machine!(@transition $($check_kind $check_dest)?),
+                    persistence: $crate::state_machine!(@persistence $($held_unit $held)?),
+                };
+                // The number of checks was asserted above
+                if checks.push(check).is_err() {
+                    unreachable!();
+                }
+            )*
+
+            #[allow(unused_mut)]
+            let mut commands = $crate::heapless::Vec::new();
+            $(
+                let command = $crate::index::Command::new(
+                    $crate::CommandObject::$command $command_arg,
+                    $crate::state_machine!(@delay $delay_unit $delay),
+                );
+                // The number of commands was asserted above
+                if commands.push(command).is_err() {
+                    unreachable!();
+                }
+            )*
+
+            let timeout =
+                $crate::state_machine!(@timeout $($time_unit $time => $timeout_kind $timeout_dest)?);
+
+            let mut name = $crate::index::StateName::new();
+            // The length of the name was asserted above
+            if name.push_str(stringify!($name)).is_err() {
+                unreachable!();
+            }
+
+            let state = $crate::index::State::new(checks, commands, timeout).with_name(name);
+            // The number of states was asserted above
+            if states.push(state).is_err() {
+                unreachable!();
+            }
+        )*
+
+        $crate::index::ConfigFile {
+            default_state: $default,
+            states,
+            hardware: None,
+        }
+    }};
+
+    // Declares a `StateIndex` constant for each state name, numbered in declaration order
+    (@indices ($index:expr)) => {};
+    (@indices ($index:expr) $name:ident $($rest:ident)*) => {
+        // States that nothing transitions to, and that aren't the default, are never referred to
+        #[allow(non_upper_case_globals, dead_code)]
+        // # SAFETY: States are pushed in the same order that indices are assigned in, so this
+        // index always refers to the state named `$name`
+        const $name: $crate::index::StateIndex =
+            unsafe { $crate::index::StateIndex::new_unchecked($index) };
+
+        $crate::state_machine!(@indices ($index + 1) $($rest)*);
+    };
+
+    // The limit given on the `limits` line, or the default
+    (@limit $default:path) => {
+        $default
+    };
+    (@limit $limit:tt $default:path) => {
+        $limit
+    };
+
+    (@count $($item:ident)*) => {
+        <[&str]>::len(&[$(stringify!($item)),*])
+    };
+
+    (@timeout) => {
+        None
+    };
+    (@timeout $unit:ident $time:expr => $kind:ident $dest:ident) => {
+        Some($crate::index::Timeout::new(
+            $crate::state_machine!(@delay $unit $time),
+            $crate::state_machine!(@transition_kind $kind $dest),
+        ))
+    };
+
+    (@delay after $seconds:expr) => {
+        $crate::Delay::Seconds($seconds)
+    };
+    (@delay after_ticks $ticks:expr) => {
+        $crate::Delay::Ticks($ticks)
+    };
+
+    (@persistence) => {
+        None
+    };
+    (@persistence held_for $seconds:expr) => {
+        Some($crate::Delay::Seconds($seconds))
+    };
+    (@persistence held_for_ticks $ticks:expr) => {
+        Some($crate::Delay::Ticks($ticks))
+    };
+
+    (@transition) => {
+        None
+    };
+    (@transition $kind:ident $dest:ident) => {
+        Some($crate::state_machine!(@transition_kind $kind $dest))
+    };
+
+    (@transition_kind transition $dest:ident) => {
+        $crate::index::StateTransition::Transition($dest)
+    };
+    (@transition_kind abort $dest:ident) => {
+        $crate::index::StateTransition::Abort($dest)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::{Check, Command, ConfigFile, State, StateIndex, StateTransition, Timeout};
+    use crate::{
+        CheckData, CommandObject, Delay, FloatCondition, NativeFlagCondition,
+        PyroContinuityCondition,
+    };
+    use heapless::Vec;
+
+    #[test]
+    fn test_state_machine_macro() {
+        let config = state_machine! {
+            limits 5 states, 4 checks, 2 commands;
+            default Poweron;
+
+            state Safe {}
+
+            state Descent {
+                command DataRate(20) after 0.0;
+                command Beacon(true) after_ticks 10;
+            }
+
+            state Flight {
+                check ApogeeFlag(NativeFlagCondition(true)) => transition Descent;
+            }
+
+            state Launch {
+                check Altitude(FloatCondition::GreaterThan(200.0)) => transition Flight;
+            }
+
+            state Poweron {
+                check Pyro1Continuity(PyroContinuityCondition(false)) => abort Safe;
+                check Pyro2Continuity(PyroContinuityCondition(false)) held_for_ticks 5 => abort Safe;
+                check Pyro3Continuity(PyroContinuityCondition(false)) held_for 0.5;
+                check Altitude(FloatCondition::GreaterThan(50.0)) => abort Safe;
+                timeout after 1.0 => transition Launch;
+            }
+        };
+
+        // # SAFETY: Only used for comparison against the macro's output
+        let idx = |i| unsafe { StateIndex::new_unchecked(i) };
+
+        let mut states = Vec::new();
+        states
+            .push(State::new(Vec::new(), Vec::new(), None).with_name("Safe".into()))
+            .unwrap();
+
+        let mut descent_commands = Vec::new();
+        descent_commands
+            .push(Command::new(
+                CommandObject::DataRate(20),
+                Delay::Seconds(0.0),
+            ))
+            .unwrap();
+        descent_commands
+            .push(Command::new(CommandObject::Beacon(true), Delay::Ticks(10)))
+            .unwrap();
+        states
+            .push(State::new(Vec::new(), descent_commands, None).with_name("Descent".into()))
+            .unwrap();
+
+        let mut flight_checks = Vec::new();
+        flight_checks
+            .push(Check::new(
+                CheckData::ApogeeFlag(NativeFlagCondition(true)),
+                Some(StateTransition::Transition(idx(1))),
+            ))
+            .unwrap();
+        states
+            .push(State::new(flight_checks, Vec::new(), None).with_name("Flight".into()))
+            .unwrap();
+
+        let mut launch_checks = Vec::new();
+        launch_checks
+            .push(Check::new(
+                CheckData::Altitude(FloatCondition::GreaterThan(200.0)),
+                Some(StateTransition::Transition(idx(2))),
+            ))
+            .unwrap();
+        states
+            .push(State::new(launch_checks, Vec::new(), None).with_name("Launch".into()))
+            .unwrap();
+
+        let mut poweron_checks = Vec::new();
+        poweron_checks
+            .push(Check::new(
+                CheckData::Pyro1Continuity(PyroContinuityCondition(false)),
+                Some(StateTransition::Abort(idx(0))),
+            ))
+            .unwrap();
+        poweron_checks
+            .push(
+                Check::new(
+                    CheckData::Pyro2Continuity(PyroContinuityCondition(false)),
+                    Some(StateTransition::Abort(idx(0))),
+                )
+                .with_persistence(Delay::Ticks(5)),
+            )
+            .unwrap();
+        poweron_checks
+            .push(
+                Check::new(
+                    CheckData::Pyro3Continuity(PyroContinuityCondition(false)),
+                    None,
+                )
+                .with_persistence(Delay::Seconds(0.5)),
+            )
+            .unwrap();
+        poweron_checks
+            .push(Check::new(
+                CheckData::Altitude(FloatCondition::GreaterThan(50.0)),
+                Some(StateTransition::Abort(idx(0))),
+            ))
+            .unwrap();
+        let timeout = Timeout::new(Delay::Seconds(1.0), StateTransition::Transition(idx(3)));
+        states
+            .push(State::new(poweron_checks, Vec::new(), Some(timeout)).with_name("Poweron".into()))
+            .unwrap();
+
+        let expected = ConfigFile::<5, 4, 2> {
+            default_state: idx(4),
+            states,
+            hardware: None,
+        };
+
+        assert_eq!(config, expected);
+    }
+}
@@ -0,0 +1,263 @@
+//! Compile time upper bounds on the serialized size of the config structures.
+//!
+//! The config file is serialized with postcard when it is sent from the verifier to the flight
+//! computer and stored in flash. These bounds let us fail the build if the largest possible config
+//! no longer fits in the flash region reserved for it, rather than truncating it at runtime.
+
+use crate::{
+    index, CheckData, CommandObject, Delay, FloatCondition, GeofenceCondition, NativeFlagCondition,
+    PyroContinuityCondition, Seconds, MAX_ANALOG_CHANNELS, PYRO_CHANNELS,
+};
+
+use heapless::Vec;
+
+/// Types whose postcard serialization has a known maximum size
+pub trait MaxSize {
+    /// The maximum number of bytes a value of this type can take up when serialized
+    const MAX_SIZE: usize;
+}
+
+/// The number of bytes postcard uses to encode `value` as a varint
+pub const fn varint_size(value: usize) -> usize {
+    let mut value = value >> 7;
+    let mut bytes = 1;
+    while value != 0 {
+        value >>= 7;
+        bytes += 1;
+    }
+    bytes
+}
+
+/// The larger of `a` and `b`, for computing the size of an enum from the size of its variants
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// The maximum size of an enum discriminant. Enum discriminants are encoded as a varint of the
+/// variant index, so this holds for any enum with less than 128 variants
+pub const DISCRIMINANT_SIZE: usize = 1;
+
+impl MaxSize for bool {
+    const MAX_SIZE: usize = 1;
+}
+
+impl MaxSize for u8 {
+    const MAX_SIZE: usize = 1;
+}
+
+impl MaxSize for u16 {
+    const MAX_SIZE: usize = varint_size(u16::MAX as usize);
+}
+
+impl MaxSize for u32 {
+    const MAX_SIZE: usize = varint_size(u32::MAX as usize);
+}
+
+impl MaxSize for f32 {
+    const MAX_SIZE: usize = 4;
+}
+
+impl<T: MaxSize> MaxSize for Option<T> {
+    const MAX_SIZE: usize = 1 + T::MAX_SIZE;
+}
+
+impl<T: MaxSize, const N: usize> MaxSize for Vec<T, N> {
+    const MAX_SIZE: usize = varint_size(N) + N * T::MAX_SIZE;
+}
+
+impl<const N: usize> MaxSize for heapless::String<N> {
+    const MAX_SIZE: usize = varint_size(N) + N;
+}
+
+// NOTE: These must be kept in sync with the definitions of the types they describe. Adding a
+// field or a variant without updating the bound here will make the bound too small.
+
+impl MaxSize for Seconds {
+    const MAX_SIZE: usize = f32::MAX_SIZE;
+}
+
+impl MaxSize for Delay {
+    const MAX_SIZE: usize = DISCRIMINANT_SIZE + max(f32::MAX_SIZE, u32::MAX_SIZE);
+}
+
+impl MaxSize for NativeFlagCondition {
+    const MAX_SIZE: usize = bool::MAX_SIZE;
+}
+
+impl MaxSize for PyroContinuityCondition {
+    const MAX_SIZE: usize = bool::MAX_SIZE;
+}
+
+impl MaxSize for GeofenceCondition {
+    const MAX_SIZE: usize = f32::MAX_SIZE;
+}
+
+impl MaxSize for FloatCondition {
+    const MAX_SIZE: usize = DISCRIMINANT_SIZE + 2 * f32::MAX_SIZE;
+}
+
+impl MaxSize for CheckData {
+    const MAX_SIZE: usize = DISCRIMINANT_SIZE
+        + max(
+            FloatCondition::MAX_SIZE,
+            max(
+                NativeFlagCondition::MAX_SIZE,
+                max(
+                    PyroContinuityCondition::MAX_SIZE,
+                    GeofenceCondition::MAX_SIZE,
+                ),
+            ),
+        );
+}
+
+impl MaxSize for CommandObject {
+    const MAX_SIZE: usize = DISCRIMINANT_SIZE
+        + max(
+            bool::MAX_SIZE,
+            max(u16::MAX_SIZE, u8::MAX_SIZE + u16::MAX_SIZE),
+        );
+}
+
+impl MaxSize for index::StateIndex {
+    const MAX_SIZE: usize = u8::MAX_SIZE;
+}
+
+impl MaxSize for index::StateTransition {
+    const MAX_SIZE: usize = DISCRIMINANT_SIZE + index::StateIndex::MAX_SIZE;
+}
+
+impl MaxSize for index::Timeout {
+    const MAX_SIZE: usize = Delay::MAX_SIZE + index::StateTransition::MAX_SIZE;
+}
+
+impl MaxSize for index::Check {
+    const MAX_SIZE: usize = CheckData::MAX_SIZE
+        + Option::<index::StateTransition>::MAX_SIZE
+        + Option::<Delay>::MAX_SIZE;
+}
+
+impl MaxSize for index::Command {
+    const MAX_SIZE: usize = CommandObject::MAX_SIZE + Delay::MAX_SIZE;
+}
+
+impl<const CHECKS: usize, const COMMANDS: usize> MaxSize for index::State<CHECKS, COMMANDS> {
+    const MAX_SIZE: usize = Option::<index::StateName>::MAX_SIZE
+        + Vec::<index::Check, CHECKS>::MAX_SIZE
+        + Vec::<index::Command, COMMANDS>::MAX_SIZE
+        + Option::<index::Timeout>::MAX_SIZE;
+}
+
+impl MaxSize for index::AnalogChannel {
+    const MAX_SIZE: usize = u8::MAX_SIZE + 2 * u16::MAX_SIZE;
+}
+
+impl MaxSize for index::HardwareTable {
+    const MAX_SIZE: usize =
+        PYRO_CHANNELS * bool::MAX_SIZE + Vec::<index::AnalogChannel, MAX_ANALOG_CHANNELS>::MAX_SIZE;
+}
+
+impl<const STATES: usize, const CHECKS: usize, const COMMANDS: usize> MaxSize
+    for index::ConfigFile<STATES, CHECKS, COMMANDS>
+{
+    const MAX_SIZE: usize = index::StateIndex::MAX_SIZE
+        + Vec::<index::State<CHECKS, COMMANDS>, STATES>::MAX_SIZE
+        + Option::<index::HardwareTable>::MAX_SIZE;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{varint_size, MaxSize};
+    use crate::index::{
+        AnalogChannel, Check, Command, ConfigFile, HardwareTable, State, StateIndex,
+        StateTransition, Timeout,
+    };
+    use crate::{
+        CheckData, CommandObject, Delay, FloatCondition, MAX_ANALOG_CHANNELS, MAX_CHECKS_PER_STATE,
+        MAX_COMMANDS_PER_STATE, MAX_STATES, MAX_STATE_NAME_LEN, PYRO_CHANNELS,
+    };
+    use heapless::Vec;
+
+    #[test]
+    fn test_varint_size() {
+        assert_eq!(varint_size(0), 1);
+        assert_eq!(varint_size(127), 1);
+        assert_eq!(varint_size(128), 2);
+        assert_eq!(varint_size(16383), 2);
+        assert_eq!(varint_size(16384), 3);
+        assert_eq!(varint_size(u16::MAX as usize), 3);
+    }
+
+    /// Serializes the largest config with the given limits and checks it against the bound
+    fn assert_bound_is_exact<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>() {
+        // # SAFETY: Only used to check the serialized size, this config is never run
+        let last_idx = unsafe { StateIndex::new_unchecked(STATES as u8 - 1) };
+
+        let mut checks = Vec::new();
+        for _ in 0..CHECKS {
+            let data = CheckData::Altitude(FloatCondition::Between {
+                upper_bound: 1.0,
+                lower_bound: 0.0,
+            });
+            let check = Check::new(data, Some(StateTransition::Abort(last_idx)))
+                .with_persistence(Delay::Ticks(u32::MAX));
+            checks.push(check).unwrap();
+        }
+
+        let mut commands = Vec::new();
+        for _ in 0..COMMANDS {
+            let object = CommandObject::AnalogOut {
+                channel: u8::MAX,
+                value: u16::MAX,
+            };
+            let command = Command::new(object, Delay::Ticks(u32::MAX));
+            commands.push(command).unwrap();
+        }
+
+        let timeout = Timeout::new(
+            Delay::Ticks(u32::MAX),
+            StateTransition::Transition(last_idx),
+        );
+        let name = "x".repeat(MAX_STATE_NAME_LEN);
+        let state = State::new(checks, commands, Some(timeout)).with_name(name.as_str().into());
+
+        let mut states = Vec::new();
+        for _ in 0..STATES {
+            states.push(state.clone()).unwrap();
+        }
+
+        let mut analog_channels = Vec::new();
+        for _ in 0..MAX_ANALOG_CHANNELS {
+            let channel = AnalogChannel {
+                channel: u8::MAX,
+                min: u16::MAX,
+                max: u16::MAX,
+            };
+            analog_channels.push(channel).unwrap();
+        }
+
+        let config = ConfigFile::<STATES, CHECKS, COMMANDS> {
+            default_state: last_idx,
+            states,
+            hardware: Some(HardwareTable {
+                pyro_channels: [true; PYRO_CHANNELS],
+                analog_channels,
+            }),
+        };
+
+        let bytes = postcard::to_allocvec(&config).unwrap();
+        assert_eq!(
+            bytes.len(),
+            ConfigFile::<STATES, CHECKS, COMMANDS>::MAX_SIZE
+        );
+    }
+
+    #[test]
+    fn test_config_file_bound_is_exact() {
+        assert_bound_is_exact::<MAX_STATES, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE>();
+        assert_bound_is_exact::<4, 6, 6>();
+    }
+}
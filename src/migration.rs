@@ -0,0 +1,923 @@
+//! Upgrades serialized config files written for older versions of the config format.
+//!
+//! Every time the postcard encoding of [`ConfigFile`] changes, [`CONFIG_FORMAT_VERSION`] is bumped
+//! and the previous layout is frozen in a `vN` module along with a step that upgrades it to the
+//! next version. [`migrate`] decodes the old layout and applies each step in turn.
+//!
+//! A frozen layout has its own copy of every type it encodes, down to the enums inside checks and
+//! commands, so that later changes to the live types can't change it. Types that didn't change
+//! between versions are shared with the version that introduced them.
+
+use crate::index::ConfigFile;
+
+use serde::Deserialize;
+
+/// The version of the config format described by [`ConfigFile`]
+pub const CONFIG_FORMAT_VERSION: u8 = 5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// There is no migration path from this version
+    UnsupportedVersion(u8),
+    /// The bytes are not a valid config file of the version they were said to be, or the config
+    /// has more states, checks, or commands than the limits it was decoded with
+    Decode,
+}
+
+/// Decodes `bytes` as a config file written for `from_version` of the config format and upgrades
+/// it to the current version
+pub fn migrate<const STATES: usize, const CHECKS: usize, const COMMANDS: usize>(
+    bytes: &[u8],
+    from_version: u8,
+) -> Result<ConfigFile<STATES, CHECKS, COMMANDS>, MigrationError> {
+    let upgraded: ConfigFile = match from_version {
+        1 => decode::<v1::ConfigFile>(bytes)
+            .map(v1::upgrade)
+            .map(v2::upgrade)
+            .map(v3::upgrade)
+            .map(v4::upgrade)?,
+        2 => decode::<v2::ConfigFile>(bytes)
+            .map(v2::upgrade)
+            .map(v3::upgrade)
+            .map(v4::upgrade)?,
+        3 => decode::<v3::ConfigFile>(bytes)
+            .map(v3::upgrade)
+            .map(v4::upgrade)?,
+        4 => decode::<v4::ConfigFile>(bytes).map(v4::upgrade)?,
+        CONFIG_FORMAT_VERSION => return decode(bytes),
+        version => return Err(MigrationError::UnsupportedVersion(version)),
+    };
+    // Old layouts are upgraded with the default limits
+    upgraded.with_limits().ok_or(MigrationError::Decode)
+}
+
+fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, MigrationError> {
+    postcard::from_bytes(bytes).map_err(|_| MigrationError::Decode)
+}
+
+/// The original config format, before the hardware table was added
+mod v1 {
+    use super::v2;
+    use crate::index::StateIndex;
+
+    use heapless::Vec;
+    use serde::{Deserialize, Serialize};
+
+    pub const MAX_STATES: usize = 16;
+    pub const MAX_CHECKS_PER_STATE: usize = 3;
+    pub const MAX_COMMANDS_PER_STATE: usize = 3;
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct ConfigFile {
+        pub default_state: StateIndex,
+        pub states: Vec<State, MAX_STATES>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct State {
+        pub checks: Vec<Check, MAX_CHECKS_PER_STATE>,
+        pub commands: Vec<Command, MAX_COMMANDS_PER_STATE>,
+        pub timeout: Option<Timeout>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Timeout {
+        /// In seconds
+        pub time: f32,
+        pub transition: StateTransition,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Check {
+        pub data: CheckData,
+        pub transition: Option<StateTransition>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum StateTransition {
+        Transition(StateIndex),
+        Abort(StateIndex),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Command {
+        pub object: CommandObject,
+        /// In seconds
+        pub delay: f32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum CheckData {
+        Altitude(FloatCondition),
+        ApogeeFlag(bool),
+        Pyro1Continuity(bool),
+        Pyro2Continuity(bool),
+        Pyro3Continuity(bool),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum FloatCondition {
+        GreaterThan(f32),
+        LessThan(f32),
+        Between { upper_bound: f32, lower_bound: f32 },
+    }
+
+    /// `AnalogOut` was added before the format was versioned. Adding a variant to the end of an
+    /// enum doesn't change how the others are encoded, so configs without it decode the same way.
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum CommandObject {
+        Pyro1(bool),
+        Pyro2(bool),
+        Pyro3(bool),
+        Beacon(bool),
+        DataRate(u16),
+        AnalogOut { channel: u8, value: u16 },
+    }
+
+    pub fn upgrade(old: ConfigFile) -> v2::ConfigFile {
+        // Both versions have the same limits
+        let states = old
+            .states
+            .into_iter()
+            .map(|state| v2::State {
+                checks: state.checks.iter().map(|&c| c.into()).collect(),
+                commands: state.commands,
+                timeout: state.timeout,
+            })
+            .collect();
+
+        v2::ConfigFile {
+            default_state: old.default_state,
+            states,
+            hardware: None,
+        }
+    }
+
+    impl From<Check> for v2::Check {
+        fn from(check: Check) -> Self {
+            let data = match check.data {
+                CheckData::Altitude(c) => v2::CheckData::Altitude(c),
+                CheckData::ApogeeFlag(flag) => v2::CheckData::ApogeeFlag(flag),
+                CheckData::Pyro1Continuity(c) => v2::CheckData::Pyro1Continuity(c),
+                CheckData::Pyro2Continuity(c) => v2::CheckData::Pyro2Continuity(c),
+                CheckData::Pyro3Continuity(c) => v2::CheckData::Pyro3Continuity(c),
+            };
+            v2::Check {
+                data,
+                transition: check.transition,
+            }
+        }
+    }
+}
+
+/// The config format before command delays and timeouts could be given in ticks
+mod v2 {
+    use super::v1::{
+        Command, FloatCondition, StateTransition, Timeout, MAX_CHECKS_PER_STATE,
+        MAX_COMMANDS_PER_STATE, MAX_STATES,
+    };
+    use super::v3;
+    use crate::index::StateIndex;
+
+    use heapless::Vec;
+    use serde::{Deserialize, Serialize};
+
+    pub const PYRO_CHANNELS: usize = 3;
+    pub const MAX_ANALOG_CHANNELS: usize = 8;
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct ConfigFile {
+        pub default_state: StateIndex,
+        pub states: Vec<State, MAX_STATES>,
+        pub hardware: Option<HardwareTable>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct State {
+        pub checks: Vec<Check, MAX_CHECKS_PER_STATE>,
+        pub commands: Vec<Command, MAX_COMMANDS_PER_STATE>,
+        pub timeout: Option<Timeout>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Check {
+        pub data: CheckData,
+        pub transition: Option<StateTransition>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum CheckData {
+        Altitude(FloatCondition),
+        ApogeeFlag(bool),
+        Pyro1Continuity(bool),
+        Pyro2Continuity(bool),
+        Pyro3Continuity(bool),
+        UplinkStale(bool),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct HardwareTable {
+        pub pyro_channels: [bool; PYRO_CHANNELS],
+        pub analog_channels: Vec<AnalogChannel, MAX_ANALOG_CHANNELS>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct AnalogChannel {
+        pub channel: u8,
+        pub min: u16,
+        pub max: u16,
+    }
+
+    pub fn upgrade(old: ConfigFile) -> v3::ConfigFile {
+        // Both versions have the same limits
+        let states = old
+            .states
+            .into_iter()
+            .map(|state| v3::State {
+                checks: state.checks.iter().map(|&c| c.into()).collect(),
+                commands: state
+                    .commands
+                    .iter()
+                    .map(|c| v3::Command {
+                        object: c.object,
+                        delay: v3::Delay::Seconds(c.delay),
+                    })
+                    .collect(),
+                timeout: state.timeout.map(|t| v3::Timeout {
+                    time: v3::Delay::Seconds(t.time),
+                    transition: t.transition,
+                }),
+            })
+            .collect();
+
+        v3::ConfigFile {
+            default_state: old.default_state,
+            states,
+            hardware: old.hardware,
+        }
+    }
+
+    impl From<Check> for v3::Check {
+        fn from(check: Check) -> Self {
+            let data = match check.data {
+                CheckData::Altitude(c) => v3::CheckData::Altitude(c),
+                CheckData::ApogeeFlag(flag) => v3::CheckData::ApogeeFlag(flag),
+                CheckData::Pyro1Continuity(c) => v3::CheckData::Pyro1Continuity(c),
+                CheckData::Pyro2Continuity(c) => v3::CheckData::Pyro2Continuity(c),
+                CheckData::Pyro3Continuity(c) => v3::CheckData::Pyro3Continuity(c),
+                CheckData::UplinkStale(flag) => v3::CheckData::UplinkStale(flag),
+            };
+            v3::Check {
+                data,
+                transition: check.transition,
+            }
+        }
+    }
+}
+
+/// The config format before checks could require their condition to persist
+mod v3 {
+    use super::v1::{
+        CommandObject, FloatCondition, StateTransition, MAX_CHECKS_PER_STATE,
+        MAX_COMMANDS_PER_STATE, MAX_STATES,
+    };
+    use super::v2::HardwareTable;
+    use super::v4;
+    use crate::index::StateIndex;
+
+    use heapless::Vec;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct ConfigFile {
+        pub default_state: StateIndex,
+        pub states: Vec<State, MAX_STATES>,
+        pub hardware: Option<HardwareTable>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct State {
+        pub checks: Vec<Check, MAX_CHECKS_PER_STATE>,
+        pub commands: Vec<Command, MAX_COMMANDS_PER_STATE>,
+        pub timeout: Option<Timeout>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Timeout {
+        pub time: Delay,
+        pub transition: StateTransition,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Check {
+        pub data: CheckData,
+        pub transition: Option<StateTransition>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Command {
+        pub object: CommandObject,
+        pub delay: Delay,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum Delay {
+        Seconds(f32),
+        Ticks(u32),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum CheckData {
+        Altitude(FloatCondition),
+        ApogeeFlag(bool),
+        Pyro1Continuity(bool),
+        Pyro2Continuity(bool),
+        Pyro3Continuity(bool),
+        UplinkStale(bool),
+        VerticalVelocity(FloatCondition),
+        Acceleration(FloatCondition),
+        TiltAngle(FloatCondition),
+    }
+
+    pub fn upgrade(old: ConfigFile) -> v4::ConfigFile {
+        // Both versions have the same limits
+        let states = old
+            .states
+            .into_iter()
+            .map(|state| v4::State {
+                checks: state.checks.iter().map(|&c| c.into()).collect(),
+                commands: state.commands,
+                timeout: state.timeout,
+            })
+            .collect();
+
+        v4::ConfigFile {
+            default_state: old.default_state,
+            states,
+            hardware: old.hardware,
+        }
+    }
+
+    impl From<Check> for v4::Check {
+        fn from(check: Check) -> Self {
+            let data = match check.data {
+                CheckData::Altitude(c) => v4::CheckData::Altitude(c.into()),
+                CheckData::ApogeeFlag(flag) => v4::CheckData::ApogeeFlag(flag),
+                CheckData::Pyro1Continuity(c) => v4::CheckData::Pyro1Continuity(c),
+                CheckData::Pyro2Continuity(c) => v4::CheckData::Pyro2Continuity(c),
+                CheckData::Pyro3Continuity(c) => v4::CheckData::Pyro3Continuity(c),
+                CheckData::UplinkStale(flag) => v4::CheckData::UplinkStale(flag),
+                CheckData::VerticalVelocity(c) => v4::CheckData::VerticalVelocity(c.into()),
+                CheckData::Acceleration(c) => v4::CheckData::Acceleration(c.into()),
+                CheckData::TiltAngle(c) => v4::CheckData::TiltAngle(c.into()),
+            };
+            v4::Check {
+                data,
+                transition: check.transition,
+                persistence: None,
+            }
+        }
+    }
+
+    impl From<FloatCondition> for v4::FloatCondition {
+        fn from(condition: FloatCondition) -> Self {
+            match condition {
+                FloatCondition::GreaterThan(threshold) => {
+                    v4::FloatCondition::GreaterThan(threshold)
+                }
+                FloatCondition::LessThan(threshold) => v4::FloatCondition::LessThan(threshold),
+                FloatCondition::Between {
+                    upper_bound,
+                    lower_bound,
+                } => v4::FloatCondition::Between {
+                    upper_bound,
+                    lower_bound,
+                },
+            }
+        }
+    }
+}
+
+/// The config format before states had names
+mod v4 {
+    use super::v1::{
+        CommandObject, StateTransition, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE, MAX_STATES,
+    };
+    use super::v2::HardwareTable;
+    use super::v3::{Command, Delay, Timeout};
+    use crate::index::{self, StateIndex};
+    use crate::{GeofenceCondition, NativeFlagCondition, PyroContinuityCondition};
+
+    use heapless::Vec;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct ConfigFile {
+        pub default_state: StateIndex,
+        pub states: Vec<State, MAX_STATES>,
+        pub hardware: Option<HardwareTable>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct State {
+        pub checks: Vec<Check, MAX_CHECKS_PER_STATE>,
+        pub commands: Vec<Command, MAX_COMMANDS_PER_STATE>,
+        pub timeout: Option<Timeout>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub struct Check {
+        pub data: CheckData,
+        pub transition: Option<StateTransition>,
+        pub persistence: Option<Delay>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum CheckData {
+        Altitude(FloatCondition),
+        ApogeeFlag(bool),
+        Pyro1Continuity(bool),
+        Pyro2Continuity(bool),
+        Pyro3Continuity(bool),
+        UplinkStale(bool),
+        VerticalVelocity(FloatCondition),
+        Acceleration(FloatCondition),
+        TiltAngle(FloatCondition),
+        Geofence { max_downrange: f32 },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+    pub enum FloatCondition {
+        GreaterThan(f32),
+        LessThan(f32),
+        Between { upper_bound: f32, lower_bound: f32 },
+        GreaterThanWithHysteresis { threshold: f32, band: f32 },
+        LessThanWithHysteresis { threshold: f32, band: f32 },
+    }
+
+    pub fn upgrade(old: ConfigFile) -> index::ConfigFile {
+        // Both versions have the same limits
+        let states = old
+            .states
+            .into_iter()
+            .map(|state| {
+                index::State::new(
+                    state.checks.iter().map(|&c| c.into()).collect(),
+                    state.commands.iter().map(|&c| c.into()).collect(),
+                    state.timeout.map(Into::into),
+                )
+            })
+            .collect();
+
+        index::ConfigFile {
+            default_state: old.default_state,
+            states,
+            hardware: old.hardware.map(|hardware| index::HardwareTable {
+                pyro_channels: hardware.pyro_channels,
+                analog_channels: hardware
+                    .analog_channels
+                    .iter()
+                    .map(|a| index::AnalogChannel {
+                        channel: a.channel,
+                        min: a.min,
+                        max: a.max,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    impl From<Check> for index::Check {
+        fn from(check: Check) -> Self {
+            let data = match check.data {
+                CheckData::Altitude(c) => crate::CheckData::Altitude(c.into()),
+                CheckData::ApogeeFlag(flag) => {
+                    crate::CheckData::ApogeeFlag(NativeFlagCondition(flag))
+                }
+                CheckData::Pyro1Continuity(c) => {
+                    crate::CheckData::Pyro1Continuity(PyroContinuityCondition(c))
+                }
+                CheckData::Pyro2Continuity(c) => {
+                    crate::CheckData::Pyro2Continuity(PyroContinuityCondition(c))
+                }
+                CheckData::Pyro3Continuity(c) => {
+                    crate::CheckData::Pyro3Continuity(PyroContinuityCondition(c))
+                }
+                CheckData::UplinkStale(flag) => {
+                    crate::CheckData::UplinkStale(NativeFlagCondition(flag))
+                }
+                CheckData::VerticalVelocity(c) => crate::CheckData::VerticalVelocity(c.into()),
+                CheckData::Acceleration(c) => crate::CheckData::Acceleration(c.into()),
+                CheckData::TiltAngle(c) => crate::CheckData::TiltAngle(c.into()),
+                CheckData::Geofence { max_downrange } => {
+                    crate::CheckData::Geofence(GeofenceCondition { max_downrange })
+                }
+            };
+            index::Check {
+                data,
+                transition: check.transition.map(Into::into),
+                persistence: check.persistence.map(Into::into),
+            }
+        }
+    }
+
+    impl From<FloatCondition> for crate::FloatCondition {
+        fn from(condition: FloatCondition) -> Self {
+            match condition {
+                FloatCondition::GreaterThan(threshold) => Self::GreaterThan(threshold),
+                FloatCondition::LessThan(threshold) => Self::LessThan(threshold),
+                FloatCondition::Between {
+                    upper_bound,
+                    lower_bound,
+                } => Self::Between {
+                    upper_bound,
+                    lower_bound,
+                },
+                FloatCondition::GreaterThanWithHysteresis { threshold, band } => {
+                    Self::GreaterThanWithHysteresis { threshold, band }
+                }
+                FloatCondition::LessThanWithHysteresis { threshold, band } => {
+                    Self::LessThanWithHysteresis { threshold, band }
+                }
+            }
+        }
+    }
+
+    impl From<Command> for index::Command {
+        fn from(command: Command) -> Self {
+            let object = match command.object {
+                CommandObject::Pyro1(on) => crate::CommandObject::Pyro1(on),
+                CommandObject::Pyro2(on) => crate::CommandObject::Pyro2(on),
+                CommandObject::Pyro3(on) => crate::CommandObject::Pyro3(on),
+                CommandObject::Beacon(on) => crate::CommandObject::Beacon(on),
+                CommandObject::DataRate(rate) => crate::CommandObject::DataRate(rate),
+                CommandObject::AnalogOut { channel, value } => {
+                    crate::CommandObject::AnalogOut { channel, value }
+                }
+            };
+            index::Command::new(object, command.delay.into())
+        }
+    }
+
+    impl From<Timeout> for index::Timeout {
+        fn from(timeout: Timeout) -> Self {
+            index::Timeout::new(timeout.time.into(), timeout.transition.into())
+        }
+    }
+
+    impl From<StateTransition> for index::StateTransition {
+        fn from(transition: StateTransition) -> Self {
+            match transition {
+                StateTransition::Transition(state) => Self::Transition(state),
+                StateTransition::Abort(state) => Self::Abort(state),
+            }
+        }
+    }
+
+    impl From<Delay> for crate::Delay {
+        fn from(delay: Delay) -> Self {
+            match delay {
+                Delay::Seconds(seconds) => Self::Seconds(seconds),
+                Delay::Ticks(ticks) => Self::Ticks(ticks),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migrate, MigrationError, CONFIG_FORMAT_VERSION};
+    use crate::index::{
+        self, AnalogChannel, ConfigFile, HardwareTable, StateIndex, StateTransition,
+    };
+    use crate::{
+        CheckData, CommandObject, Delay, FloatCondition, GeofenceCondition, NativeFlagCondition,
+        PyroContinuityCondition,
+    };
+    use heapless::Vec;
+
+    // # SAFETY: Each config in these tests has two states
+    const SECOND: StateIndex = unsafe { StateIndex::new_unchecked(1) };
+
+    /// Removes the names `state_machine!` gives states, which configs from before version 5 can't
+    /// have
+    fn unnamed(mut config: ConfigFile) -> ConfigFile {
+        for state in &mut config.states {
+            state.name = None;
+        }
+        config
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        // Encoded by the original, unversioned config types
+        #[rustfmt::skip]
+        let bytes = [
+            // Default state, and two states
+            1, 2,
+            // Two checks: Altitude(Between(1000.0, 200.0)) => Transition(1), and
+            // Pyro1Continuity(false) => Abort(1)
+            2, 0, 2, 0, 0, 122, 68, 0, 0, 72, 67, 1, 0, 1, 2, 0, 1, 1, 1,
+            // Two commands: Beacon(true) after 2.5 s, and DataRate(300) after 0 s
+            2, 3, 1, 0, 0, 32, 64, 4, 172, 2, 0, 0, 0, 0,
+            // Timeout after 1.0 s => Transition(1)
+            1, 0, 0, 128, 63, 0, 1,
+            // One check in the second state: ApogeeFlag(true)
+            1, 1, 1, 0, 0, 0,
+        ];
+
+        // The old layout can't be read as the current one
+        assert!(postcard::from_bytes::<ConfigFile>(&bytes).is_err());
+
+        let expected = state_machine! {
+            default Second;
+
+            state First {
+                check Altitude(FloatCondition::Between {
+                    upper_bound: 1000.0,
+                    lower_bound: 200.0,
+                }) => transition Second;
+                check Pyro1Continuity(PyroContinuityCondition(false)) => abort Second;
+                command Beacon(true) after 2.5;
+                command DataRate(300) after 0.0;
+                timeout after 1.0 => transition Second;
+            }
+
+            state Second {
+                check ApogeeFlag(NativeFlagCondition(true));
+            }
+        };
+        assert_eq!(migrate(&bytes, 1), Ok(unnamed(expected)));
+    }
+
+    #[test]
+    fn test_migrate_v2() {
+        #[rustfmt::skip]
+        let bytes = [
+            // Default state, and two states
+            1, 2,
+            // One check: Pyro2Continuity(true) => Abort(1)
+            1, 3, 1, 1, 1, 1,
+            // Two commands: AnalogOut(3, 512) after 0.5 s, and Pyro3(true) after 0 s
+            2, 5, 3, 128, 4, 0, 0, 0, 63, 2, 1, 0, 0, 0, 0,
+            // Timeout after 2.0 s => Abort(1)
+            1, 0, 0, 0, 64, 1, 1,
+            // Empty second state
+            0, 0, 0,
+            // Hardware table: pyro channels, then analog channel 3 from 100 to 900
+            1, 1, 0, 1, 1, 3, 100, 132, 7,
+        ];
+
+        let mut expected = unnamed(state_machine! {
+            default Second;
+
+            state First {
+                check Pyro2Continuity(PyroContinuityCondition(true)) => abort Second;
+                command AnalogOut { channel: 3, value: 512 } after 0.5;
+                command Pyro3(true) after 0.0;
+                timeout after 2.0 => abort Second;
+            }
+
+            state Second {}
+        });
+        let mut analog_channels = Vec::new();
+        analog_channels
+            .push(AnalogChannel {
+                channel: 3,
+                min: 100,
+                max: 900,
+            })
+            .unwrap();
+        expected.hardware = Some(HardwareTable {
+            pyro_channels: [true, false, true],
+            analog_channels,
+        });
+        assert_eq!(migrate(&bytes, 2), Ok(expected));
+    }
+
+    #[test]
+    fn test_migrate_v3() {
+        #[rustfmt::skip]
+        let bytes = [
+            // Default state, and two states
+            1, 2,
+            // Three checks: TiltAngle(GreaterThan(30.0)) => Abort(1), UplinkStale(true) =>
+            // Transition(1), and VerticalVelocity(LessThan(0.0))
+            3, 8, 0, 0, 0, 240, 65, 1, 1, 1, 5, 1, 1, 0, 1, 6, 1, 0, 0, 0, 0, 0,
+            // One command: Pyro1(true) after 250 ticks
+            1, 0, 1, 1, 250, 1,
+            // Timeout after 3.0 s => Transition(1)
+            1, 0, 0, 0, 64, 64, 0, 1,
+            // Empty second state, and no hardware table
+            0, 0, 0, 0,
+        ];
+
+        let expected = state_machine! {
+            default Second;
+
+            state First {
+                check TiltAngle(FloatCondition::GreaterThan(30.0)) => abort Second;
+                check UplinkStale(NativeFlagCondition(true)) => transition Second;
+                check VerticalVelocity(FloatCondition::LessThan(0.0));
+                command Pyro1(true) after_ticks 250;
+                timeout after 3.0 => transition Second;
+            }
+
+            state Second {}
+        };
+        assert_eq!(migrate(&bytes, 3), Ok(unnamed(expected)));
+        assert_eq!(migrate::<16, 2, 3>(&bytes, 3), Err(MigrationError::Decode));
+    }
+
+    #[test]
+    fn test_migrate_current() {
+        let config = snapshot_config();
+        let bytes = postcard::to_allocvec(&config).unwrap();
+
+        assert_eq!(migrate(&bytes, CONFIG_FORMAT_VERSION), Ok(config));
+        // The first state has a check, so it doesn't fit in a config without any
+        assert_eq!(
+            migrate::<16, 0, 3>(&bytes, CONFIG_FORMAT_VERSION),
+            Err(MigrationError::Decode)
+        );
+
+        let truncated: Result<ConfigFile, _> =
+            migrate(&bytes[..bytes.len() - 1], CONFIG_FORMAT_VERSION);
+        assert_eq!(truncated, Err(MigrationError::Decode));
+        let unknown: Result<ConfigFile, _> = migrate(&bytes, 0);
+        assert_eq!(unknown, Err(MigrationError::UnsupportedVersion(0)));
+    }
+
+    /// A config that uses every field, for pinning the encoding
+    fn snapshot_config() -> ConfigFile {
+        let mut checks = Vec::new();
+        checks
+            .push(
+                index::Check::new(
+                    CheckData::Acceleration(FloatCondition::GreaterThan(3.0)),
+                    Some(StateTransition::Transition(SECOND)),
+                )
+                .with_persistence(Delay::Ticks(10)),
+            )
+            .unwrap();
+        let mut commands = Vec::new();
+        commands
+            .push(index::Command::new(
+                CommandObject::Pyro2(true),
+                Delay::Seconds(0.5),
+            ))
+            .unwrap();
+        let timeout = index::Timeout::new(Delay::Ticks(1000), StateTransition::Abort(SECOND));
+
+        let mut states = Vec::new();
+        states
+            .push(index::State::new(checks, commands, Some(timeout)))
+            .unwrap();
+        states
+            .push(index::State::new(Vec::new(), Vec::new(), None))
+            .unwrap();
+
+        let mut analog_channels = Vec::new();
+        analog_channels
+            .push(AnalogChannel {
+                channel: 0,
+                min: 0,
+                max: 1023,
+            })
+            .unwrap();
+
+        ConfigFile {
+            default_state: SECOND,
+            states,
+            hardware: Some(HardwareTable {
+                pyro_channels: [true, true, false],
+                analog_channels,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_migrate_v4() {
+        // The v4 encoding of `snapshot_config`, before states had names
+        #[rustfmt::skip]
+        let v4_bytes = [
+            // Default state, and two states
+            1, 2,
+            // One check: Acceleration(GreaterThan(3.0)) => Transition(1), held for 10 ticks
+            1, 7, 0, 0, 0, 64, 64, 1, 0, 1, 1, 1, 10,
+            // One command: Pyro2(true) after 0.5 s
+            1, 1, 1, 0, 0, 0, 0, 63,
+            // Timeout after 1000 ticks => Abort(1)
+            1, 1, 232, 7, 1, 1,
+            // Empty second state
+            0, 0, 0,
+            // Hardware table: pyro channels, then analog channel 0 from 0 to 1023
+            1, 1, 1, 0, 1, 0, 0, 255, 7,
+        ];
+        assert_eq!(migrate(&v4_bytes, 4), Ok(snapshot_config()));
+    }
+
+    /// Pins the encoding of the current format version. If this fails, the encoding has changed:
+    /// bump `CONFIG_FORMAT_VERSION` and freeze the old layout in a new `vN` module before updating
+    /// the fixtures.
+    #[test]
+    fn test_encoding_snapshot() {
+        assert_eq!(CONFIG_FORMAT_VERSION, 5);
+
+        let hysteresis = FloatCondition::GreaterThanWithHysteresis {
+            threshold: 30.0,
+            band: 5.0,
+        };
+        let checks: [(CheckData, &[u8]); 11] = [
+            (
+                CheckData::Altitude(FloatCondition::GreaterThan(200.0)),
+                &[0, 0, 0, 0, 72, 67],
+            ),
+            (CheckData::ApogeeFlag(NativeFlagCondition(true)), &[1, 1]),
+            (
+                CheckData::Pyro1Continuity(PyroContinuityCondition(true)),
+                &[2, 1],
+            ),
+            (
+                CheckData::Pyro2Continuity(PyroContinuityCondition(false)),
+                &[3, 0],
+            ),
+            (
+                CheckData::Pyro3Continuity(PyroContinuityCondition(true)),
+                &[4, 1],
+            ),
+            (CheckData::UplinkStale(NativeFlagCondition(true)), &[5, 1]),
+            (
+                CheckData::VerticalVelocity(FloatCondition::LessThan(0.0)),
+                &[6, 1, 0, 0, 0, 0],
+            ),
+            (
+                CheckData::Acceleration(FloatCondition::Between {
+                    upper_bound: 2.0,
+                    lower_bound: -1.0,
+                }),
+                &[7, 2, 0, 0, 0, 64, 0, 0, 128, 191],
+            ),
+            (
+                CheckData::TiltAngle(hysteresis),
+                &[8, 3, 0, 0, 240, 65, 0, 0, 160, 64],
+            ),
+            (
+                CheckData::Geofence(GeofenceCondition {
+                    max_downrange: 3000.0,
+                }),
+                &[9, 0, 128, 59, 69],
+            ),
+            (
+                CheckData::Altitude(FloatCondition::LessThanWithHysteresis {
+                    threshold: 30.0,
+                    band: 5.0,
+                }),
+                &[0, 4, 0, 0, 240, 65, 0, 0, 160, 64],
+            ),
+        ];
+        for (data, bytes) in checks {
+            assert_eq!(postcard::to_allocvec(&data).unwrap(), bytes, "{data:?}");
+        }
+
+        let commands: [(CommandObject, &[u8]); 6] = [
+            (CommandObject::Pyro1(true), &[0, 1]),
+            (CommandObject::Pyro2(false), &[1, 0]),
+            (CommandObject::Pyro3(true), &[2, 1]),
+            (CommandObject::Beacon(true), &[3, 1]),
+            (CommandObject::DataRate(300), &[4, 172, 2]),
+            (
+                CommandObject::AnalogOut {
+                    channel: 2,
+                    value: 512,
+                },
+                &[5, 2, 128, 4],
+            ),
+        ];
+        for (object, bytes) in commands {
+            assert_eq!(postcard::to_allocvec(&object).unwrap(), bytes, "{object:?}");
+        }
+
+        let mut config = snapshot_config();
+        config.states[0] = config.states[0].clone().with_name("Boost".into());
+
+        #[rustfmt::skip]
+        let expected = [
+            // Default state, and two states
+            1, 2,
+            // Named "Boost"
+            1, 5, b'B', b'o', b'o', b's', b't',
+            // One check: Acceleration(GreaterThan(3.0)) => Transition(1), held for 10 ticks
+            1, 7, 0, 0, 0, 64, 64, 1, 0, 1, 1, 1, 10,
+            // One command: Pyro2(true) after 0.5 s
+            1, 1, 1, 0, 0, 0, 0, 63,
+            // Timeout after 1000 ticks => Abort(1)
+            1, 1, 232, 7, 1, 1,
+            // Unnamed, empty second state
+            0, 0, 0, 0,
+            // Hardware table: pyro channels, then analog channel 0 from 0 to 1023
+            1, 1, 1, 0, 1, 0, 0, 255, 7,
+        ];
+        assert_eq!(postcard::to_allocvec(&config).unwrap(), expected);
+    }
+}
@@ -6,35 +6,52 @@ use core::cell::Cell;
 use core::sync::atomic::AtomicBool;
 use heapless::Vec;
 
+use crate::index::StateName;
 use crate::{frozen::FrozenVec, MAX_CHECKS_PER_STATE, MAX_COMMANDS_PER_STATE, MAX_STATES};
 
-pub struct ConfigFile<'s> {
-    pub default_state: &'s State<'s>,
-    pub states: Vec<&'s State<'s>, MAX_STATES>,
+pub struct ConfigFile<
+    's,
+    const STATES: usize = MAX_STATES,
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
+    pub default_state: &'s State<'s, CHECKS, COMMANDS>,
+    pub states: Vec<&'s State<'s, CHECKS, COMMANDS>, STATES>,
 }
 
-pub struct Timeout<'s> {
-    pub time: f32,
-    pub transition: StateTransition<'s>,
+#[derive(Copy, Clone)]
+pub struct Timeout<
+    's,
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
+    pub time: crate::Delay,
+    pub transition: StateTransition<'s, CHECKS, COMMANDS>,
 }
 
-impl<'s> Timeout<'s> {
-    pub fn new(time: f32, transition: StateTransition<'s>) -> Self {
+impl<'s, const CHECKS: usize, const COMMANDS: usize> Timeout<'s, CHECKS, COMMANDS> {
+    pub fn new(time: crate::Delay, transition: StateTransition<'s, CHECKS, COMMANDS>) -> Self {
         Self { time, transition }
     }
 }
 
-pub struct State<'s> {
+pub struct State<
+    's,
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
     pub id: u8,
-    pub checks: FrozenVec<&'s Check<'s>, MAX_CHECKS_PER_STATE>,
-    pub commands: FrozenVec<&'s Command, MAX_COMMANDS_PER_STATE>,
-    pub timeout: Cell<Option<Timeout<'s>>>,
+    pub name: Option<StateName>,
+    pub checks: FrozenVec<&'s Check<'s, CHECKS, COMMANDS>, CHECKS>,
+    pub commands: FrozenVec<&'s Command, COMMANDS>,
+    pub timeout: Cell<Option<Timeout<'s, CHECKS, COMMANDS>>>,
 }
 
-impl<'s> State<'s> {
-    pub(crate) fn new(id: u8) -> Self {
+impl<'s, const CHECKS: usize, const COMMANDS: usize> State<'s, CHECKS, COMMANDS> {
+    pub(crate) fn new(id: u8, name: Option<StateName>) -> Self {
         Self {
             id,
+            name,
             checks: FrozenVec::new(),
             commands: FrozenVec::new(),
             timeout: Cell::new(None),
@@ -43,12 +60,14 @@ impl<'s> State<'s> {
 
     pub fn new_complete(
         id: u8,
-        checks: FrozenVec<&'s Check<'s>, MAX_CHECKS_PER_STATE>,
-        commands: FrozenVec<&'s Command, MAX_COMMANDS_PER_STATE>,
-        timeout: Option<Timeout<'s>>,
+        name: Option<StateName>,
+        checks: FrozenVec<&'s Check<'s, CHECKS, COMMANDS>, CHECKS>,
+        commands: FrozenVec<&'s Command, COMMANDS>,
+        timeout: Option<Timeout<'s, CHECKS, COMMANDS>>,
     ) -> Self {
         Self {
             id,
+            name,
             checks,
             commands,
             timeout: Cell::new(timeout),
@@ -56,21 +75,43 @@ impl<'s> State<'s> {
     }
 }
 
-pub struct Check<'s> {
+pub struct Check<
+    's,
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
     pub data: crate::CheckData,
-    pub transition: Option<StateTransition<'s>>,
+    pub transition: Option<StateTransition<'s, CHECKS, COMMANDS>>,
+    /// How long the condition must hold continuously before the transition is made
+    pub persistence: Option<crate::Delay>,
 }
 
-impl<'s> Check<'s> {
-    pub fn new(data: crate::CheckData, transition: Option<StateTransition<'s>>) -> Self {
-        Self { data, transition }
+impl<'s, const CHECKS: usize, const COMMANDS: usize> Check<'s, CHECKS, COMMANDS> {
+    pub fn new(
+        data: crate::CheckData,
+        transition: Option<StateTransition<'s, CHECKS, COMMANDS>>,
+    ) -> Self {
+        Self {
+            data,
+            transition,
+            persistence: None,
+        }
+    }
+
+    pub fn with_persistence(mut self, persistence: crate::Delay) -> Self {
+        self.persistence = Some(persistence);
+        self
     }
 }
 
 #[derive(Copy, Clone)]
-pub enum StateTransition<'s> {
-    Transition(&'s State<'s>),
-    Abort(&'s State<'s>),
+pub enum StateTransition<
+    's,
+    const CHECKS: usize = MAX_CHECKS_PER_STATE,
+    const COMMANDS: usize = MAX_COMMANDS_PER_STATE,
+> {
+    Transition(&'s State<'s, CHECKS, COMMANDS>),
+    Abort(&'s State<'s, CHECKS, COMMANDS>),
 }
 
 /// An action that takes place at a specific time after the state containing this is entered
@@ -80,14 +121,14 @@ pub struct Command {
     pub object: crate::CommandObject,
 
     /// How long after the state activates to execute this command
-    pub delay: crate::Seconds,
+    pub delay: crate::Delay,
 
     /// If this command has already executed
     pub was_executed: AtomicBool,
 }
 
 impl Command {
-    pub fn new(object: crate::CommandObject, delay: crate::Seconds) -> Self {
+    pub fn new(object: crate::CommandObject, delay: crate::Delay) -> Self {
         Self {
             object,
             delay,
@@ -0,0 +1,296 @@
+//! A fixed capacity byte ring buffer shared by the telemetry scheduler and the logging pipeline.
+
+/// A heapless, fixed capacity FIFO of bytes
+///
+/// Data can be borrowed as contiguous slices on both the read and the write side so that it can be
+/// handed directly to a DMA transfer without copying. An optional high watermark callback is
+/// invoked whenever a write causes the buffer's length to reach the watermark. `N` must be greater
+/// than zero.
+///
+/// ```
+/// use nova_software_common::ring::RingBuffer;
+///
+/// let mut ring = RingBuffer::<8>::new();
+///
+/// ring.write(&[1, 2, 3]);
+/// assert_eq!(ring.pop(), Some(1));
+/// ```
+pub struct RingBuffer<const N: usize> {
+    buffer: [u8; N],
+    /// Index of the oldest byte in the buffer
+    head: usize,
+    len: usize,
+    watermark: Option<Watermark>,
+}
+
+#[derive(Clone, Copy)]
+struct Watermark {
+    level: usize,
+    callback: fn(usize),
+}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Constructs a new, empty ring buffer with a fixed capacity of `N` bytes
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N > 0,
+                "a RingBuffer must have a capacity of at least one byte"
+            )
+        };
+        Self {
+            buffer: [0; N],
+            head: 0,
+            len: 0,
+            watermark: None,
+        }
+    }
+
+    /// Constructs a new, empty ring buffer that calls `callback` with the current length every
+    /// time a write brings the length from below `level` to at least `level`
+    pub const fn with_watermark(level: usize, callback: fn(usize)) -> Self {
+        const {
+            assert!(
+                N > 0,
+                "a RingBuffer must have a capacity of at least one byte"
+            )
+        };
+        Self {
+            buffer: [0; N],
+            head: 0,
+            len: 0,
+            watermark: Some(Watermark { level, callback }),
+        }
+    }
+
+    /// Appends a single `byte` to the back of the buffer
+    ///
+    /// Returns back the `byte` if the buffer is full
+    pub fn push(&mut self, byte: u8) -> Result<(), u8> {
+        if self.is_full() {
+            return Err(byte);
+        }
+
+        let tail = self.tail();
+        self.buffer[tail] = byte;
+        self.commit(1);
+        Ok(())
+    }
+
+    /// Appends as many bytes from `data` as will fit, returning the number of bytes written
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        let old_len = self.len;
+        let mut written = 0;
+
+        // At most two copies are needed: up to the end of the backing array, then from its start
+        while written < data.len() {
+            let free = self.write_slice();
+            if free.is_empty() {
+                break;
+            }
+            let n = free.len().min(data.len() - written);
+            free[..n].copy_from_slice(&data[written..written + n]);
+            self.len += n;
+            written += n;
+        }
+
+        self.notify(old_len);
+        written
+    }
+
+    /// Removes the oldest byte from the buffer and returns it, or `None` if it is empty
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let byte = self.buffer[self.head];
+        self.consume(1);
+        Some(byte)
+    }
+
+    /// Returns the longest contiguous slice of readable bytes, starting with the oldest byte
+    ///
+    /// This may be shorter than [`Self::len`] when the data wraps around the end of the buffer.
+    /// Once the bytes have been used, call [`Self::consume`] to release them.
+    pub fn read_slice(&self) -> &[u8] {
+        let end = (self.head + self.len).min(N);
+        &self.buffer[self.head..end]
+    }
+
+    /// Returns all readable bytes as a pair of slices in order, the second of which is only
+    /// non-empty when the data wraps around the end of the buffer
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        let first = self.read_slice();
+        let second = &self.buffer[..self.len - first.len()];
+        (first, second)
+    }
+
+    /// Releases the `n` oldest bytes from the buffer
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than [`Self::len`]
+    pub fn consume(&mut self, n: usize) {
+        assert!(n <= self.len, "consumed more bytes than are stored");
+
+        self.len -= n;
+        self.head = if self.len == 0 {
+            0
+        } else {
+            (self.head + n) % N
+        };
+    }
+
+    /// Returns the longest contiguous slice of free space at the back of the buffer
+    ///
+    /// Once bytes have been written into the slice, call [`Self::commit`] to make them readable.
+    pub fn write_slice(&mut self) -> &mut [u8] {
+        let tail = self.tail();
+        let end = if tail < self.head || self.is_full() {
+            self.head
+        } else {
+            N
+        };
+        &mut self.buffer[tail..end]
+    }
+
+    /// Marks `n` bytes previously written through [`Self::write_slice`] as readable
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the amount of free space in the buffer
+    pub fn commit(&mut self, n: usize) {
+        assert!(
+            n <= N - self.len,
+            "committed more bytes than there is space for"
+        );
+
+        let old_len = self.len;
+        self.len += n;
+        self.notify(old_len);
+    }
+
+    /// Returns true if the buffer is full
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Returns true if the buffer is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes currently stored in the buffer
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the maximum number of bytes the buffer can hold
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Index of the slot the next written byte goes into
+    fn tail(&self) -> usize {
+        (self.head + self.len) % N
+    }
+
+    /// Invokes the watermark callback if the length just crossed the watermark
+    fn notify(&self, old_len: usize) {
+        if let Some(watermark) = self.watermark {
+            if old_len < watermark.level && self.len >= watermark.level {
+                (watermark.callback)(self.len);
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_fifo_order() {
+        let mut ring = RingBuffer::<4>::new();
+
+        assert!(ring.is_empty());
+        assert_eq!(ring.write(&[1, 2, 3, 4, 5]), 4);
+        assert!(ring.is_full());
+        assert_eq!(ring.push(6), Err(6));
+
+        assert_eq!(ring.pop(), Some(1));
+        assert_eq!(ring.pop(), Some(2));
+        ring.push(7).unwrap();
+        ring.push(8).unwrap();
+
+        assert_eq!(ring.pop(), Some(3));
+        assert_eq!(ring.pop(), Some(4));
+        assert_eq!(ring.pop(), Some(7));
+        assert_eq!(ring.pop(), Some(8));
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn test_contiguous_slices() {
+        let mut ring = RingBuffer::<6>::new();
+
+        ring.write(&[1, 2, 3, 4, 5]);
+        ring.consume(3);
+        ring.write(&[6, 7, 8]);
+
+        // The data now wraps around the end of the backing array
+        assert_eq!(ring.read_slice(), &[4, 5, 6]);
+        assert_eq!(ring.as_slices(), (&[4, 5, 6][..], &[7, 8][..]));
+
+        ring.consume(3);
+        assert_eq!(ring.read_slice(), &[7, 8]);
+
+        let free = ring.write_slice();
+        assert_eq!(free.len(), 4);
+        free[..2].copy_from_slice(&[9, 10]);
+        ring.commit(2);
+
+        assert_eq!(ring.as_slices(), (&[7, 8, 9, 10][..], &[][..]));
+    }
+
+    #[test]
+    fn test_watermark() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static LAST_LEN: AtomicUsize = AtomicUsize::new(0);
+
+        fn callback(len: usize) {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            LAST_LEN.store(len, Ordering::SeqCst);
+        }
+
+        let mut ring = RingBuffer::<8>::with_watermark(4, callback);
+
+        ring.write(&[0; 3]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+        ring.write(&[0; 2]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        assert_eq!(LAST_LEN.load(Ordering::SeqCst), 5);
+
+        // Staying above the watermark doesn't notify again
+        ring.push(0).unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        // Dropping below and crossing it again does
+        ring.consume(4);
+        ring.write(&[0; 2]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+        assert_eq!(LAST_LEN.load(Ordering::SeqCst), 4);
+    }
+}
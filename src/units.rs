@@ -0,0 +1,156 @@
+//! Unit preferences for displaying values to people.
+//!
+//! Everything in this crate and on the flight computer is in SI units. Ground tools convert to the
+//! user's preferred units only when formatting values, using [`Preferences`], so that every tool
+//! shows the same numbers.
+
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+const FEET_PER_METRE: f32 = 3.280_84;
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum LengthUnit {
+    Metres,
+    Feet,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum SpeedUnit {
+    MetresPerSecond,
+    FeetPerSecond,
+    KilometresPerHour,
+    MilesPerHour,
+}
+
+/// The units a user wants values displayed in
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub struct Preferences {
+    /// Used for altitudes and distances
+    pub length: LengthUnit,
+    /// Used for velocities
+    pub speed: SpeedUnit,
+}
+
+/// A value converted into a display unit
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f32,
+    /// The unit's symbol
+    pub unit: &'static str,
+}
+
+impl LengthUnit {
+    /// The number of this unit in one metre
+    fn per_metre(self) -> f32 {
+        match self {
+            LengthUnit::Metres => 1.0,
+            LengthUnit::Feet => FEET_PER_METRE,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            LengthUnit::Metres => "m",
+            LengthUnit::Feet => "ft",
+        }
+    }
+}
+
+impl SpeedUnit {
+    /// The number of this unit in one metre per second
+    fn per_metre_per_second(self) -> f32 {
+        match self {
+            SpeedUnit::MetresPerSecond => 1.0,
+            SpeedUnit::FeetPerSecond => FEET_PER_METRE,
+            SpeedUnit::KilometresPerHour => 3.6,
+            SpeedUnit::MilesPerHour => FEET_PER_METRE * 3600.0 / 5280.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            SpeedUnit::MetresPerSecond => "m/s",
+            SpeedUnit::FeetPerSecond => "ft/s",
+            SpeedUnit::KilometresPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+        }
+    }
+}
+
+impl Preferences {
+    pub const METRIC: Self = Self {
+        length: LengthUnit::Metres,
+        speed: SpeedUnit::MetresPerSecond,
+    };
+
+    pub const IMPERIAL: Self = Self {
+        length: LengthUnit::Feet,
+        speed: SpeedUnit::MilesPerHour,
+    };
+
+    /// Converts a length in metres into the preferred unit
+    pub fn length(&self, metres: f32) -> Quantity {
+        Quantity {
+            value: metres * self.length.per_metre(),
+            unit: self.length.symbol(),
+        }
+    }
+
+    /// Converts a speed in metres per second into the preferred unit
+    pub fn speed(&self, metres_per_second: f32) -> Quantity {
+        Quantity {
+            value: metres_per_second * self.speed.per_metre_per_second(),
+            unit: self.speed.symbol(),
+        }
+    }
+
+    /// Converts a length entered by the user in the preferred unit back into metres
+    pub fn length_to_metres(&self, length: f32) -> f32 {
+        length / self.length.per_metre()
+    }
+
+    /// Converts a speed entered by the user in the preferred unit back into metres per second
+    pub fn speed_to_metres_per_second(&self, speed: f32) -> f32 {
+        speed / self.speed.per_metre_per_second()
+    }
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self::METRIC
+    }
+}
+
+impl fmt::Display for Quantity {
+    /// Formats the value with one decimal place unless another precision is given
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        write!(f, "{:.*} {}", precision, self.value, self.unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Preferences, SpeedUnit};
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_conversions() {
+        let imperial = Preferences::IMPERIAL;
+        assert_eq!(imperial.length(1000.0).to_string(), "3280.8 ft");
+        assert_eq!(imperial.speed(100.0).to_string(), "223.7 mph");
+        assert_eq!(Preferences::METRIC.length(1000.0).to_string(), "1000.0 m");
+
+        let prefs = Preferences {
+            speed: SpeedUnit::KilometresPerHour,
+            ..imperial
+        };
+        assert_eq!(alloc::format!("{:.0}", prefs.speed(10.0)), "36 km/h");
+
+        let metres = imperial.length_to_metres(imperial.length(152.4).value);
+        assert!((metres - 152.4).abs() < 1e-3);
+        let speed = prefs.speed_to_metres_per_second(36.0);
+        assert!((speed - 10.0).abs() < 1e-4);
+    }
+}